@@ -37,7 +37,7 @@ Adafruit_MPU6050 mpu;
 sensors_event_t a, g, temp;
 
 void setup() {
-  Serial.begin(9600);
+  Serial.begin(115200);
 
   //tenta iniciar
   if (!mpu.begin()) {
@@ -69,10 +69,27 @@ void loop() {
   pitch = atan2((- a.acceleration.x) , sqrt(a.acceleration.y * a.acceleration.y + a.acceleration.z * a.acceleration.z));
   roll = atan2(a.acceleration.y , a.acceleration.z); 
 
-  //imprime valores
+  /*
+  imprime valores: pitch, roll, aceleração (m/s²), giroscópio (rad/s) e temperatura (°C).
+  O pitch e o roll do host vêm da fusão do acelerômetro com o giroscópio.
+  */
   Serial.print(lado);
   Serial.print(pitch,6);
   Serial.print("\t");
-  Serial.println(roll,6);
+  Serial.print(roll,6);
+  Serial.print("\t");
+  Serial.print(a.acceleration.x,4);
+  Serial.print("\t");
+  Serial.print(a.acceleration.y,4);
+  Serial.print("\t");
+  Serial.print(a.acceleration.z,4);
+  Serial.print("\t");
+  Serial.print(g.gyro.x,5);
+  Serial.print("\t");
+  Serial.print(g.gyro.y,5);
+  Serial.print("\t");
+  Serial.print(g.gyro.z,5);
+  Serial.print("\t");
+  Serial.println(temp.temperature,2);
   delay(50);
 }
@@ -0,0 +1,107 @@
+/*
+Arco de movimento: o maior ângulo (graus) que o pé alcançou em cada direção, medido a partir
+do offset de repouso. Cada direção só é medida enquanto o movimento dela está sendo previsto,
+para que a inclinação lateral de uma dorsiflexão não conte como eversão.
+
+Sinais, pelos dados coletados: dorsiflexão tem pitch negativo e flexão plantar positivo;
+eversão tem roll positivo no pé esquerdo e negativo no direito.
+*/
+
+use crate::{dado_papete::DadoPapete, movimento::Movimento};
+
+use serde::{Deserialize, Serialize};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ArcoMovimento {
+    pub dorsiflexao: f32,
+    pub flexao: f32,
+    pub eversao: f32,
+    pub inversao: f32,
+}
+
+impl ArcoMovimento {
+    /*
+    Recebe o movimento previsto e o dado corrigido (sem offset) do mesmo instante
+    */
+    pub fn atualizar(&mut self, movimento: Movimento, dado: &DadoPapete) {
+        let graus = componente(movimento, dado).to_degrees();
+        match movimento {
+            Movimento::Dorsiflexao => self.dorsiflexao = self.dorsiflexao.max(graus),
+            Movimento::Flexao => self.flexao = self.flexao.max(graus),
+            Movimento::Eversao => self.eversao = self.eversao.max(graus),
+            Movimento::Inversao => self.inversao = self.inversao.max(graus),
+            Movimento::Repouso => {}
+        }
+    }
+
+    /*
+    Maior de cada direção entre os dois
+    */
+    pub fn maximo(&self, outro: &ArcoMovimento) -> ArcoMovimento {
+        ArcoMovimento {
+            dorsiflexao: self.dorsiflexao.max(outro.dorsiflexao),
+            flexao: self.flexao.max(outro.flexao),
+            eversao: self.eversao.max(outro.eversao),
+            inversao: self.inversao.max(outro.inversao),
+        }
+    }
+
+    pub fn graus(&self, movimento: Movimento) -> f32 {
+        match movimento {
+            Movimento::Dorsiflexao => self.dorsiflexao,
+            Movimento::Flexao => self.flexao,
+            Movimento::Eversao => self.eversao,
+            Movimento::Inversao => self.inversao,
+            Movimento::Repouso => 0.0,
+        }
+    }
+}
+
+/*
+Quanto o dado corrigido avança (rad) na direção do movimento, já com o sinal de cada pé;
+negativo quando vai para o lado oposto. Zero para o repouso.
+*/
+pub fn componente(movimento: Movimento, dado: &DadoPapete) -> f32 {
+    //positivo para eversão nos dois pés
+    let roll = if dado.lado_esq { 1.0 } else { -1.0 } * dado.roll;
+    match movimento {
+        Movimento::Dorsiflexao => -dado.pitch,
+        Movimento::Flexao => dado.pitch,
+        Movimento::Eversao => roll,
+        Movimento::Inversao => -roll,
+        Movimento::Repouso => 0.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arco::*;
+
+    #[test]
+    fn direcoes_por_pe() {
+        let mut esq = ArcoMovimento::default();
+        let mut dir = ArcoMovimento::default();
+        let rad = 20f32.to_radians();
+        esq.atualizar(Movimento::Dorsiflexao, &DadoPapete::basico(-rad, 0.1, true));
+        //fora do movimento previsto não conta
+        esq.atualizar(
+            Movimento::Repouso,
+            &DadoPapete::basico(-2.0 * rad, 0.0, true),
+        );
+        esq.atualizar(Movimento::Eversao, &DadoPapete::basico(0.0, rad, true));
+        dir.atualizar(Movimento::Eversao, &DadoPapete::basico(0.0, -rad, false));
+        dir.atualizar(
+            Movimento::Inversao,
+            &DadoPapete::basico(0.0, 0.5 * rad, false),
+        );
+
+        assert!((esq.dorsiflexao - 20.0).abs() < 1e-4);
+        assert!((esq.eversao - 20.0).abs() < 1e-4 && esq.inversao == 0.0);
+        assert!((dir.eversao - 20.0).abs() < 1e-4);
+        assert!((dir.inversao - 10.0).abs() < 1e-4);
+        let total = esq.maximo(&dir);
+        assert_eq!(total.graus(Movimento::Inversao), dir.inversao);
+        assert_eq!(total.graus(Movimento::Flexao), 0.0);
+    }
+}
@@ -1,71 +1,302 @@
+use crate::balanceamento::{self, ConfigTreino};
+use crate::caracteristicas::{self, EspecCaracteristicas, FluxoGravado, JanelaCaracteristicas};
 use crate::dado_papete::DadoPapete;
+use crate::lado::PorLado;
 use crate::movimento::Movimento;
-use crate::previsor::Previsor;
+use crate::previsor::{Previsor, ResultadoTransferencia};
 
 use rustlearn::multiclass::OneVsRestWrapper;
 use rustlearn::prelude::*;
 use rustlearn::trees::decision_tree::{DecisionTree, Hyperparameters};
+use std::time::Instant;
 
-pub struct Arvore(OneVsRestWrapper<DecisionTree>);
+pub struct Arvore {
+    modelo: OneVsRestWrapper<DecisionTree>,
+    caracteristicas: EspecCaracteristicas,
+    //para prever recebendo um dado por vez, quando as características usam janela:
+    //amostras ao vivo usam a hora da chegada, e as gravadas, o seu instante_ms
+    janelas: PorLado<JanelaCaracteristicas>,
+    gravados: PorLado<FluxoGravado>,
+}
 
-impl Previsor for Arvore {
-    fn calcular_de_dataset(dataset: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut model = Hyperparameters::new(3)
+impl Arvore {
+    /*
+    Árvore sobre as características da especificação, extraídas do dataset como o fluxo de
+    cada pé. A árvore do rustlearn não aceita pesos, então cada amostra é repetida conforme o
+    seu peso. Com janela, a reamostragem vira só os pesos de classe, já que sortear amostras
+    quebraria a ordem do fluxo, e toda amostra precisa de instante_ms: o tempo entre elas é o
+    que as características medem.
+    */
+    pub fn calcular_com_caracteristicas(
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+        espec: EspecCaracteristicas,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let instantanea = espec == EspecCaracteristicas::instantanea();
+        if !instantanea && dataset.iter().any(|d| d.instante_ms.is_none()) {
+            simple_error::bail!("Características com janela precisam do instante de cada amostra")
+        }
+        let (dados, pesos) = if instantanea {
+            balanceamento::preparar(dataset, pesos_amostras, config)
+        } else {
+            let pesos = balanceamento::pesos_por_amostra(dataset, pesos_amostras, config);
+            (dataset.to_vec(), pesos)
+        };
+        let entradas =
+            caracteristicas::extrair_dataset(&espec, &dados, caracteristicas::PERIODO_COLETA);
+        let saidas: Vec<f32> = dados
+            .iter()
+            .map(|x| x.movimento.unwrap().as_f32())
+            .collect();
+        let entradas = balanceamento::replicar_por_peso(&entradas, &pesos);
+        let saidas = balanceamento::replicar_por_peso(&saidas, &pesos);
+
+        let mut model = Hyperparameters::new(espec.tamanho())
             .min_samples_split(5)
             .max_depth(40)
             .one_vs_rest();
+        model.fit(&Array::from(&entradas), &Array::from(saidas))?;
+        Ok(Arvore::de_modelo(model, espec))
+    }
 
-        let entradas: Vec<Vec<f32>> = dataset
-            .iter()
-            .map(|x| Vec::from(x.array_normalizado()))
-            .collect();
-        let entradas = Array::from(&entradas);
+    fn de_modelo(
+        modelo: OneVsRestWrapper<DecisionTree>,
+        caracteristicas: EspecCaracteristicas,
+    ) -> Arvore {
+        let origem = Instant::now();
+        Arvore {
+            modelo,
+            janelas: PorLado::new(
+                JanelaCaracteristicas::new(caracteristicas.clone()),
+                JanelaCaracteristicas::new(caracteristicas.clone()),
+            ),
+            gravados: PorLado::new(
+                FluxoGravado::new(
+                    caracteristicas.clone(),
+                    origem,
+                    caracteristicas::PERIODO_COLETA,
+                ),
+                FluxoGravado::new(
+                    caracteristicas.clone(),
+                    origem,
+                    caracteristicas::PERIODO_COLETA,
+                ),
+            ),
+            caracteristicas,
+        }
+    }
 
-        let saidas: Vec<f32> = dataset
+    fn prever_entradas(&self, entradas: &[Vec<f32>]) -> Vec<Movimento> {
+        self.modelo
+            .predict(&Array::from(&entradas.to_vec()))
+            .unwrap()
+            .data()
             .iter()
-            .map(|x| x.movimento.unwrap().as_f32())
-            .collect();
-        let saidas = Array::from(saidas);
+            .map(|x| Movimento::try_from(*x as i32).unwrap())
+            .collect()
+    }
+}
 
-        model.fit(&entradas, &saidas).unwrap();
-        Ok(Arvore(model))
+impl Previsor for Arvore {
+    fn calcular_de_dataset(dataset: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::calcular_de_dataset_com(dataset, None, &ConfigTreino::default())
+    }
+    fn calcular_de_dataset_com(
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::calcular_com_caracteristicas(
+            dataset,
+            pesos_amostras,
+            config,
+            EspecCaracteristicas::instantanea(),
+        )
     }
+    /*
+    Aceita o JSON só com o modelo, das árvores instantâneas, ou com o modelo e as características
+    */
     fn carregar(endereco: &str) -> Result<Self, Box<dyn std::error::Error>>
     where
         Self: Sized,
     {
         if let Ok(contents) = std::fs::read_to_string(endereco) {
-            match serde_json::from_str(&contents) {
-                Ok(r) => return Ok(Arvore(r)),
-                Err(_) => simple_error::bail!("Falha ao interpretar JSON"),
+            if let Ok(mut valor) = serde_json::from_str::<serde_json::Value>(&contents) {
+                if let Some(espec) = valor.get_mut("caracteristicas").map(|v| v.take()) {
+                    if let (Ok(modelo), Ok(espec)) = (
+                        serde_json::from_value(valor["modelo"].take()),
+                        serde_json::from_value(espec),
+                    ) {
+                        return Ok(Arvore::de_modelo(modelo, espec));
+                    }
+                } else if let Ok(modelo) = serde_json::from_value(valor) {
+                    return Ok(Arvore::de_modelo(
+                        modelo,
+                        EspecCaracteristicas::instantanea(),
+                    ));
+                }
             }
+            simple_error::bail!("Falha ao interpretar JSON")
         }
         simple_error::bail!("Falha ao abrir o arquivo")
     }
     fn prever(&mut self, entrada: DadoPapete) -> Movimento {
-        let norm = Array::from(&vec![Vec::from(entrada.array_normalizado())]);
-        Movimento::try_from(self.0.predict(&norm).unwrap().get(0, 0) as i32).unwrap()
+        let entrada = if entrada.instante_ms.is_some() {
+            self.gravados[entrada.lado()].atualizar(&entrada)
+        } else {
+            self.janelas[entrada.lado()].atualizar(&entrada, Instant::now())
+        };
+        self.prever_entradas(&[entrada])[0]
     }
     fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
-        let entrada: Vec<Vec<f32>> = entrada
-            .iter()
-            .map(|x| Vec::from(x.array_normalizado()))
-            .collect();
-        let norm = Array::from(&entrada);
-        self.0
-            .predict(&norm)
-            .unwrap()
-            .data()
-            .iter()
-            .map(|x| Movimento::try_from(*x as i32).unwrap())
-            .collect()
+        let entradas = caracteristicas::extrair_dataset(
+            &self.caracteristicas,
+            entrada,
+            caracteristicas::PERIODO_COLETA,
+        );
+        self.prever_entradas(&entradas)
+    }
+    fn caracteristicas(&self) -> EspecCaracteristicas {
+        self.caracteristicas.clone()
+    }
+    fn prever_caracteristicas(&mut self, caracteristicas: &[f32], _: DadoPapete) -> Movimento {
+        self.prever_entradas(&[caracteristicas.to_vec()])[0]
     }
     fn salvar(&self, endereco: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let serialized = serde_json::to_string(&self.0).unwrap();
+        let serialized = if self.caracteristicas == EspecCaracteristicas::instantanea() {
+            serde_json::to_string(&self.modelo)?
+        } else {
+            serde_json::to_string(&serde_json::json!({
+                "modelo": &self.modelo,
+                "caracteristicas": &self.caracteristicas,
+            }))?
+        };
         std::fs::write(endereco, serialized)?;
         Ok(())
     }
-    fn transferir(&mut self, _: &[DadoPapete]) {
+    fn transferir(&mut self, _: &[DadoPapete]) -> ResultadoTransferencia {
         panic!("Transferencia não existe para arvore");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::arvore::*;
+    use crate::caracteristicas::Caracteristica;
+    use crate::lado::Lado;
+
+    //os dois pés alternam repouso e dorsiflexão, uma amostra de cada a cada 50 ms
+    fn fluxo() -> Vec<DadoPapete> {
+        let mut dados = Vec::new();
+        for i in 0..80u64 {
+            let (movimento, pitch) = if (i / 10) % 2 == 0 {
+                (Movimento::Repouso, 0.0)
+            } else {
+                (Movimento::Dorsiflexao, -0.5)
+            };
+            for lado_esq in [true, false] {
+                let mut dado = DadoPapete::completo(pitch, 0.0, lado_esq, movimento, 1);
+                dado.instante_ms = Some(1_000 + 50 * i);
+                dados.push(dado);
+            }
+        }
+        dados
+    }
+
+    #[test]
+    fn salvar_e_carregar() {
+        let dados = fluxo();
+        let espec = EspecCaracteristicas::dinamica(200);
+        let mut arvore = Arvore::calcular_com_caracteristicas(
+            &dados,
+            None,
+            &ConfigTreino::default(),
+            espec.clone(),
+        )
+        .unwrap();
+        let destino =
+            std::env::temp_dir().join(format!("papete_arvore_{}.json", std::process::id()));
+        arvore.salvar(destino.to_str().unwrap()).unwrap();
+        let salvo: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&destino).unwrap()).unwrap();
+        assert!(salvo.get("modelo").is_some());
+        assert!(salvo.get("caracteristicas").is_some());
+        let mut carregada = Arvore::carregar(destino.to_str().unwrap()).unwrap();
+        assert_eq!(carregada.caracteristicas(), espec);
+        assert_eq!(carregada.prever_batch(&dados), arvore.prever_batch(&dados));
+
+        //JSON antigo, só com o modelo: árvore instantânea
+        let instantanea = Arvore::calcular_de_dataset(&dados).unwrap();
+        std::fs::write(
+            &destino,
+            serde_json::to_string(&instantanea.modelo).unwrap(),
+        )
+        .unwrap();
+        let carregada = Arvore::carregar(destino.to_str().unwrap()).unwrap();
+        assert_eq!(
+            carregada.caracteristicas(),
+            EspecCaracteristicas::instantanea()
+        );
+        let _ = std::fs::remove_file(&destino);
+
+        //sem o instante das amostras não há como treinar com janela
+        let sem_instante: Vec<DadoPapete> = dados
+            .iter()
+            .map(|d| DadoPapete {
+                instante_ms: None,
+                ..*d
+            })
+            .collect();
+        assert!(Arvore::calcular_com_caracteristicas(
+            &sem_instante,
+            None,
+            &ConfigTreino::default(),
+            espec
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn prever_amostras_gravadas() {
+        //só conta como dorsiflexão depois de 250 ms fora do repouso; como só o tempo desde o
+        //repouso separa os movimentos, o instante de cada amostra decide a previsão
+        let dados: Vec<DadoPapete> = fluxo()
+            .into_iter()
+            .map(|mut d| {
+                if (d.instante_ms.unwrap() - 1_000) % 1_000 < 750 {
+                    d.movimento = Some(Movimento::Repouso);
+                }
+                d
+            })
+            .collect();
+        let espec = EspecCaracteristicas {
+            caracteristicas: vec![Caracteristica::TempoDesdeRepouso],
+            ..EspecCaracteristicas::dinamica(200)
+        };
+        let mut arvore =
+            Arvore::calcular_com_caracteristicas(&dados, None, &ConfigTreino::default(), espec)
+                .unwrap();
+        let batch = arvore.prever_batch(&dados);
+        assert!(batch.contains(&Movimento::Dorsiflexao));
+        let uma_a_uma: Vec<Movimento> = dados.iter().map(|d| arvore.prever(*d)).collect();
+        assert_eq!(uma_a_uma, batch);
+    }
+
+    #[test]
+    fn janela_por_pe() {
+        let espec = EspecCaracteristicas {
+            caracteristicas: vec![Caracteristica::DeltaPitch],
+            ..EspecCaracteristicas::dinamica(1_000)
+        };
+        let mut arvore =
+            Arvore::calcular_com_caracteristicas(&fluxo(), None, &ConfigTreino::default(), espec)
+                .unwrap();
+        arvore.prever(DadoPapete::basico(-0.5, 0.0, true));
+        arvore.prever(DadoPapete::basico(-0.2, 0.0, false));
+        //a variação do pé direito é sobre a amostra anterior dele, não a do esquerdo
+        let delta = arvore.janelas[Lado::Direito]
+            .atualizar(&DadoPapete::basico(-0.3, 0.0, false), Instant::now());
+        assert!((delta[0] + 0.1).abs() < 1e-6);
+    }
+}
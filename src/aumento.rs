@@ -0,0 +1,156 @@
+/*
+Aumento de dados para datasets de calibração, que costumam ter poucas repetições de cada movimento.
+Cada transformação gera cópias modificadas das amostras originais; com a mesma seed o resultado é
+sempre o mesmo.
+*/
+
+use crate::dado_papete::DadoPapete;
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/*
+Ângulos em radianos. Um valor zero desliga a transformação correspondente.
+- desvio_ruido: desvio-padrão do ruído gaussiano somado a cada amostra;
+- rotacao_max: rotação do plano (pitch, roll), simulando a tira mal posicionada;
+- deslocamento_max: erro somado ao offset de repouso, igual para toda a sessão de uma cópia;
+- espelhar: inclui também as amostras espelhadas para o outro pé.
+*/
+#[derive(Clone, Debug)]
+pub struct ConfigAumento {
+    pub copias: usize,
+    pub desvio_ruido: f32,
+    pub rotacao_max: f32,
+    pub deslocamento_max: f32,
+    pub espelhar: bool,
+    pub seed: u64,
+}
+
+impl Default for ConfigAumento {
+    fn default() -> Self {
+        ConfigAumento {
+            copias: 5,
+            desvio_ruido: 0.01,
+            rotacao_max: 0.05,
+            deslocamento_max: 0.02,
+            espelhar: false,
+            seed: 0,
+        }
+    }
+}
+
+/*
+Retorna as amostras originais seguidas das cópias aumentadas
+*/
+pub fn aumentar(dataset: &[DadoPapete], config: &ConfigAumento) -> Vec<DadoPapete> {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut saida = dataset.to_vec();
+
+    for _ in 0..config.copias {
+        let angulo = rng.gen_range(-1.0..=1.0) * config.rotacao_max;
+        //um deslocamento por sessão, já que o offset é capturado uma vez por sessão
+        let mut deslocamentos: Vec<(Option<u32>, (f32, f32))> = Vec::new();
+        for dado in dataset {
+            let deslocamento = match deslocamentos.iter().find(|d| d.0 == dado.sessao) {
+                Some(d) => d.1,
+                None => {
+                    let d = (
+                        rng.gen_range(-1.0..=1.0) * config.deslocamento_max,
+                        rng.gen_range(-1.0..=1.0) * config.deslocamento_max,
+                    );
+                    deslocamentos.push((dado.sessao, d));
+                    d
+                }
+            };
+            let mut novo = rotacionar(*dado, angulo);
+            novo = deslocar(novo, deslocamento.0, deslocamento.1);
+            novo = ruido(novo, config.desvio_ruido, &mut rng);
+            saida.push(novo);
+        }
+    }
+
+    if config.espelhar {
+        let espelhados: Vec<DadoPapete> = saida.iter().map(|d| espelhar(*d)).collect();
+        saida.extend(espelhados);
+    }
+    saida
+}
+
+/*
+Soma ruído gaussiano de desvio-padrão `desvio` ao pitch e ao roll
+*/
+pub fn ruido(mut dado: DadoPapete, desvio: f32, rng: &mut impl Rng) -> DadoPapete {
+    if desvio > 0.0 {
+        dado.pitch += gaussiana(rng) * desvio;
+        dado.roll += gaussiana(rng) * desvio;
+    }
+    dado
+}
+
+/*
+Gira o ponto (pitch, roll) em torno da origem (o repouso, já que os dados têm o offset descontado)
+*/
+pub fn rotacionar(mut dado: DadoPapete, angulo: f32) -> DadoPapete {
+    let (sen, cos) = angulo.sin_cos();
+    let (pitch, roll) = (dado.pitch, dado.roll);
+    dado.pitch = cos * pitch - sen * roll;
+    dado.roll = sen * pitch + cos * roll;
+    dado
+}
+
+pub fn deslocar(mut dado: DadoPapete, pitch: f32, roll: f32) -> DadoPapete {
+    dado.pitch += pitch;
+    dado.roll += roll;
+    dado
+}
+
+/*
+Troca o pé da amostra. Supõe que as duas papetes são montadas do mesmo jeito, então o mesmo
+roll no outro pé corresponde ao movimento lateral oposto: Eversão vira Inversão e vice-versa.
+*/
+pub fn espelhar(mut dado: DadoPapete) -> DadoPapete {
+    dado.lado_esq = !dado.lado_esq;
+    dado.movimento = dado.movimento.map(|m| m.espelhado());
+    dado
+}
+
+//Box-Muller: normal padrão a partir de duas uniformes
+fn gaussiana(rng: &mut impl Rng) -> f32 {
+    let u1: f32 = rng.gen_range(f32::EPSILON..1.0);
+    let u2: f32 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f32::consts::PI * u2).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aumento::*;
+    use crate::movimento::Movimento;
+
+    #[test]
+    fn reprodutivel() {
+        let dados = vec![
+            DadoPapete::completo(0.3, 0.0, true, Movimento::Dorsiflexao, 1),
+            DadoPapete::completo(0.0, 0.4, true, Movimento::Eversao, 1),
+        ];
+        let config = ConfigAumento {
+            espelhar: true,
+            ..Default::default()
+        };
+        let a = aumentar(&dados, &config);
+        let b = aumentar(&dados, &config);
+        assert_eq!(a.len(), 2 * (1 + config.copias) * dados.len());
+        assert!(a
+            .iter()
+            .zip(b.iter())
+            .all(|(x, y)| x.pitch == y.pitch && x.roll == y.roll));
+
+        let espelhado = a[a.len() / 2 + 1];
+        assert!(!espelhado.lado_esq);
+        assert_eq!(espelhado.movimento, Some(Movimento::Inversao));
+    }
+
+    #[test]
+    fn rotacao_preserva_amplitude() {
+        let d = rotacionar(DadoPapete::basico(0.3, 0.4, false), 0.1);
+        assert!(((d.pitch * d.pitch + d.roll * d.roll).sqrt() - 0.5).abs() < 1e-6);
+    }
+}
@@ -1,12 +1,19 @@
 use crate::{
+    balanceamento::ConfigTreino,
     csv_helper::{self, carregar_dados},
     dado_papete::DadoPapete,
     movimento::Movimento,
+    neural::ConfigTransferencia,
     previsor::Previsor,
 };
-use rand::prelude::SliceRandom;
+use rand::{prelude::SliceRandom, rngs::StdRng, SeedableRng};
+use serde::{Serialize, Serializer};
 use statistical;
 
+/*
+Em JSON, cada linha (esperado) e cada coluna (obtido) é indexada pelo nome do movimento
+*/
+#[derive(Clone, Debug)]
 pub struct MatrizConfusao([[usize; 5]; 5]);
 impl MatrizConfusao {
     pub fn new(
@@ -19,85 +26,596 @@ impl MatrizConfusao {
         }
         MatrizConfusao(matriz)
     }
+
+    /*
+    Quantidade de amostras esperadas de cada movimento (soma de cada linha)
+    */
+    pub fn suporte(&self) -> [usize; 5] {
+        self.0.map(|linha| linha.iter().sum())
+    }
+
+    /*
+    Quantidade de vezes que cada movimento foi previsto (soma de cada coluna)
+    */
+    pub fn previstos(&self) -> [usize; 5] {
+        let mut previstos = [0; 5];
+        for linha in &self.0 {
+            for (j, valor) in linha.iter().enumerate() {
+                previstos[j] += valor;
+            }
+        }
+        previstos
+    }
+
+    pub fn total(&self) -> usize {
+        self.suporte().iter().sum()
+    }
+
+    pub fn acertos(&self) -> usize {
+        (0..5).map(|i| self.0[i][i]).sum()
+    }
+
+    //linha: esperado, coluna: obtido
+    pub fn valores(&self) -> &[[usize; 5]; 5] {
+        &self.0
+    }
+}
+impl Serialize for MatrizConfusao {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            Movimento::todos()
+                .iter()
+                .map(|m| (m, PorMovimento(&self.0[m.as_usize()]))),
+        )
+    }
+}
+
+//valores indexados por Movimento::as_usize, serializados como um mapa pelo nome do movimento
+struct PorMovimento<'a, T>(&'a [T; 5]);
+
+impl<T: Serialize> Serialize for PorMovimento<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(
+            Movimento::todos()
+                .iter()
+                .map(|m| (m, &self.0[m.as_usize()])),
+        )
+    }
 }
+
+fn por_movimento<S: Serializer, T: Serialize>(
+    valores: &[T; 5],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    PorMovimento(valores).serialize(serializer)
+}
+
 impl std::fmt::Display for MatrizConfusao {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        write!(f, "e\\o")?;
+        write!(f, "e\\o ")?;
         for m in Movimento::todos() {
-            write!(f, "  {}  ", m.str_simplificada().chars().nth(0).unwrap())?;
+            write!(f, " {:>4}", &m.str_simplificada()[..3])?;
+        }
+        writeln!(f, "      n")?;
+        let suporte = self.suporte();
+        for m in Movimento::todos() {
+            let i = m.as_usize();
+            write!(f, "{:<4}", &m.str_simplificada()[..3])?;
+            for j in 0..5 {
+                write!(f, " {:>4}", self.0[i][j])?;
+            }
+            writeln!(f, " {:>6}", suporte[i])?;
         }
-        write!(f, "\n")?;
+        writeln!(f)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Serialize)]
+pub struct MetricasClasse {
+    pub precisao: f32,
+    pub revocacao: f32,
+    pub f1: f32,
+    pub suporte: usize,
+}
+
+/*
+Resultado de uma avaliação. Médias macro consideram só os movimentos presentes nos dados de teste;
+as micro coincidem com a acurácia, já que cada amostra tem exatamente um movimento.
+Intervalos de confiança são de 95%.
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct RelatorioAvaliacao {
+    pub matriz: MatrizConfusao,
+    #[serde(serialize_with = "por_movimento")]
+    pub por_classe: [MetricasClasse; 5],
+    pub acuracia: f32,
+    //Wilson, sobre todas as amostras de teste
+    pub ic_acuracia: (f32, f32),
+    pub acuracia_balanceada: f32,
+    pub macro_precisao: f32,
+    pub macro_revocacao: f32,
+    pub macro_f1: f32,
+    pub micro_f1: f32,
+    pub kappa: f32,
+    pub taxas_pastas: Vec<f32>,
+    //sessões ou grupos de teste de cada pasta, quando a validação é agrupada
+    pub grupos_pastas: Vec<Vec<u32>>,
+    pub media_pastas: f32,
+    pub desvio_pastas: f32,
+    //t de Student, sobre as taxas de acerto das pastas
+    pub ic_media_pastas: (f32, f32),
+}
+
+impl RelatorioAvaliacao {
+    pub fn new(matriz: MatrizConfusao, taxas_pastas: Vec<f32>) -> RelatorioAvaliacao {
+        let suporte = matriz.suporte();
+        let previstos = matriz.previstos();
+        let total = matriz.total();
+        let acuracia = razao(matriz.acertos(), total);
+
+        let mut por_classe = [MetricasClasse::default(); 5];
         for i in 0..5 {
-            write!(
+            let precisao = razao(matriz.0[i][i], previstos[i]);
+            let revocacao = razao(matriz.0[i][i], suporte[i]);
+            por_classe[i] = MetricasClasse {
+                precisao,
+                revocacao,
+                f1: if precisao + revocacao > 0.0 {
+                    2.0 * precisao * revocacao / (precisao + revocacao)
+                } else {
+                    0.0
+                },
+                suporte: suporte[i],
+            };
+        }
+        let presentes: Vec<&MetricasClasse> = por_classe.iter().filter(|m| m.suporte > 0).collect();
+        let media_presentes = |f: fn(&MetricasClasse) -> f32| {
+            if presentes.is_empty() {
+                0.0
+            } else {
+                presentes.iter().map(|m| f(m)).sum::<f32>() / presentes.len() as f32
+            }
+        };
+        let macro_revocacao = media_presentes(|m| m.revocacao);
+
+        //concordância esperada ao acaso
+        let esperada = (0..5)
+            .map(|i| suporte[i] as f32 * previstos[i] as f32)
+            .sum::<f32>()
+            / (total as f32 * total as f32).max(1.0);
+        let kappa = if esperada < 1.0 {
+            (acuracia - esperada) / (1.0 - esperada)
+        } else {
+            0.0
+        };
+
+        let (media_pastas, desvio_pastas) = match taxas_pastas.len() {
+            0 => (0.0, 0.0),
+            1 => (taxas_pastas[0], 0.0),
+            _ => (
+                statistical::mean(&taxas_pastas),
+                statistical::standard_deviation(&taxas_pastas, None),
+            ),
+        };
+        let margem = if taxas_pastas.len() > 1 {
+            t_critico_95(taxas_pastas.len() - 1) * desvio_pastas
+                / (taxas_pastas.len() as f32).sqrt()
+        } else {
+            0.0
+        };
+
+        RelatorioAvaliacao {
+            por_classe,
+            acuracia,
+            ic_acuracia: intervalo_wilson(matriz.acertos(), total),
+            acuracia_balanceada: macro_revocacao,
+            macro_precisao: media_presentes(|m| m.precisao),
+            macro_revocacao,
+            macro_f1: media_presentes(|m| m.f1),
+            micro_f1: acuracia,
+            kappa,
+            taxas_pastas,
+            grupos_pastas: Vec::new(),
+            media_pastas,
+            desvio_pastas,
+            ic_media_pastas: (media_pastas - margem, media_pastas + margem),
+            matriz,
+        }
+    }
+
+    /*
+    Lista as métricas em que este relatório ficou mais de `tolerancia` abaixo da referência.
+    Vazio significa que não houve regressão.
+    */
+    #[allow(dead_code)]
+    pub fn regressoes(&self, referencia: &RelatorioAvaliacao, tolerancia: f32) -> Vec<String> {
+        let mut saida = Vec::new();
+        for (nome, atual, anterior) in [
+            ("acurácia", self.acuracia, referencia.acuracia),
+            (
+                "acurácia balanceada",
+                self.acuracia_balanceada,
+                referencia.acuracia_balanceada,
+            ),
+            ("F1 macro", self.macro_f1, referencia.macro_f1),
+            ("kappa", self.kappa, referencia.kappa),
+        ] {
+            if atual < anterior - tolerancia {
+                saida.push(format!("{}: {:.4} -> {:.4}", nome, anterior, atual));
+            }
+        }
+        for m in Movimento::todos() {
+            let (atual, anterior) = (
+                self.por_classe[m.as_usize()].f1,
+                referencia.por_classe[m.as_usize()].f1,
+            );
+            if atual < anterior - tolerancia {
+                saida.push(format!("F1 {}: {:.4} -> {:.4}", m, anterior, atual));
+            }
+        }
+        saida
+    }
+}
+
+impl std::fmt::Display for RelatorioAvaliacao {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "taxas: {:?}", self.taxas_pastas)?;
+        for (taxa, grupos) in self.taxas_pastas.iter().zip(self.grupos_pastas.iter()) {
+            writeln!(f, "  {:.4} testando {:?}", taxa, grupos)?;
+        }
+        writeln!(
+            f,
+            "média: {:.4} (IC95% {:.4} a {:.4})\ndesvio-padrão: {:.4}",
+            self.media_pastas, self.ic_media_pastas.0, self.ic_media_pastas.1, self.desvio_pastas
+        )?;
+        writeln!(
+            f,
+            "acurácia: {:.4} (IC95% {:.4} a {:.4})\nacurácia balanceada: {:.4}\nkappa: {:.4}",
+            self.acuracia,
+            self.ic_acuracia.0,
+            self.ic_acuracia.1,
+            self.acuracia_balanceada,
+            self.kappa
+        )?;
+        writeln!(
+            f,
+            "macro: precisão {:.4}  revocação {:.4}  F1 {:.4}\nmicro: F1 {:.4}\n",
+            self.macro_precisao, self.macro_revocacao, self.macro_f1, self.micro_f1
+        )?;
+        writeln!(
+            f,
+            "{:<16}{:>9}{:>10}{:>8}{:>6}",
+            "", "precisão", "revocação", "F1", "n"
+        )?;
+        for m in Movimento::todos() {
+            let c = &self.por_classe[m.as_usize()];
+            writeln!(
                 f,
-                "{}  ",
-                Movimento::try_from(i as i32)
-                    .unwrap()
-                    .str_simplificada()
-                    .chars()
-                    .nth(0)
-                    .unwrap()
+                "{:<16}{:>9.4}{:>10.4}{:>8.4}{:>6}",
+                m.str_completa(),
+                c.precisao,
+                c.revocacao,
+                c.f1,
+                c.suporte
             )?;
-            for j in 0..5 {
-                write!(f, " {:^4}", self.0[i][j])?;
-            }
-            write!(f, "\n")?;
         }
-        write!(f, "\n")
+        write!(f, "\n{}", self.matriz)
+    }
+}
+
+fn razao(a: usize, b: usize) -> f32 {
+    if b == 0 {
+        0.0
+    } else {
+        a as f32 / b as f32
+    }
+}
+
+fn intervalo_wilson(acertos: usize, total: usize) -> (f32, f32) {
+    if total == 0 {
+        return (0.0, 1.0);
+    }
+    let z = 1.96;
+    let n = total as f32;
+    let p = acertos as f32 / n;
+    let centro = (p + z * z / (2.0 * n)) / (1.0 + z * z / n);
+    let margem = z / (1.0 + z * z / n) * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt();
+    ((centro - margem).max(0.0), (centro + margem).min(1.0))
+}
+
+//valor crítico bicaudal de 95% da t de Student
+pub fn t_critico_95(graus_liberdade: usize) -> f32 {
+    static TABELA: [f32; 30] = [
+        12.706, 4.303, 3.182, 2.776, 2.571, 2.447, 2.365, 2.306, 2.262, 2.228, 2.201, 2.179, 2.160,
+        2.145, 2.131, 2.120, 2.110, 2.101, 2.093, 2.086, 2.080, 2.074, 2.069, 2.064, 2.060, 2.056,
+        2.052, 2.048, 2.045, 2.042,
+    ];
+    match graus_liberdade {
+        0 => f32::INFINITY,
+        g if g <= 30 => TABELA[g - 1],
+        _ => 1.96,
+    }
+}
+
+#[allow(dead_code)]
+pub fn teste_10_pastas<T: Previsor>() -> RelatorioAvaliacao {
+    teste_10_pastas_com::<T>(&ConfigTreino::default())
+}
+
+pub fn teste_10_pastas_com<T: Previsor>(config: &ConfigTreino) -> RelatorioAvaliacao {
+    let dados = csv_helper::carregar_dados("papete.csv").unwrap();
+    validacao_cruzada::<T>(&dados, &ConfigValidacao::default(), config)
+}
+
+/*
+Como as amostras são separadas entre treino e teste:
+- Amostras: embaralha amostra por amostra. Amostras quase idênticas da mesma sessão caem
+  no treino e no teste, o que superestima a acurácia;
+- Sessao: sessões inteiras ficam de um lado só;
+- Grupo: idem, agrupando pela chave dada (por exemplo, um id de paciente).
+Com pastas = None, cada grupo vira uma pasta (leave-one-session-out / leave-one-subject-out).
+*/
+#[derive(Clone, Copy, Debug)]
+pub enum Estrategia {
+    Amostras,
+    Sessao,
+    #[allow(dead_code)]
+    Grupo(fn(&DadoPapete) -> u32),
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigValidacao {
+    pub estrategia: Estrategia,
+    pub pastas: Option<usize>,
+    pub seed: u64,
+}
+
+impl Default for ConfigValidacao {
+    fn default() -> Self {
+        ConfigValidacao {
+            estrategia: Estrategia::Amostras,
+            pastas: Some(10),
+            seed: 0,
+        }
+    }
+}
+
+impl Estrategia {
+    fn chave(&self, dado: &DadoPapete, indice: usize) -> u32 {
+        match self {
+            Estrategia::Amostras => indice as u32,
+            Estrategia::Sessao => dado.sessao.unwrap_or(0),
+            Estrategia::Grupo(chave) => chave(dado),
+        }
     }
 }
 
-pub fn teste_10_pastas<T: Previsor>() {
-    //teste 10pastas
-    let mut dados = csv_helper::carregar_dados("papete.csv").unwrap();
-    dados.shuffle(&mut rand::thread_rng());
+/*
+Índices de teste de cada pasta e os grupos que ela contém.
+Grupos são distribuídos do maior para o menor, sempre na pasta com menos amostras.
+*/
+pub fn separar_pastas(
+    dados: &[DadoPapete],
+    config: &ConfigValidacao,
+) -> Vec<(Vec<u32>, Vec<usize>)> {
+    let mut grupos: Vec<(u32, Vec<usize>)> = Vec::new();
+    for (i, dado) in dados.iter().enumerate() {
+        let chave = config.estrategia.chave(dado, i);
+        match grupos.iter_mut().find(|g| g.0 == chave) {
+            Some(g) => g.1.push(i),
+            None => grupos.push((chave, vec![i])),
+        }
+    }
+    grupos.shuffle(&mut StdRng::seed_from_u64(config.seed));
+    //estável: grupos de mesmo tamanho mantêm a ordem sorteada
+    grupos.sort_by_key(|g| std::cmp::Reverse(g.1.len()));
 
+    let qtd_pastas = config
+        .pastas
+        .unwrap_or(grupos.len())
+        .clamp(1, grupos.len().max(1));
+    let mut pastas: Vec<(Vec<u32>, Vec<usize>)> = vec![(Vec::new(), Vec::new()); qtd_pastas];
+    for (chave, indices) in grupos {
+        let menor = pastas.iter_mut().min_by_key(|p| p.1.len()).unwrap();
+        menor.0.push(chave);
+        menor.1.extend(indices);
+    }
+    pastas
+}
+
+pub fn validacao_cruzada<T: Previsor>(
+    dados: &[DadoPapete],
+    config_validacao: &ConfigValidacao,
+    config_treino: &ConfigTreino,
+) -> RelatorioAvaliacao {
+    let pastas = separar_pastas(dados, config_validacao);
+
+    let mut esperado = Vec::with_capacity(dados.len());
     let mut obtido = Vec::with_capacity(dados.len());
-    let tamanho_pasta = dados.len() / 10;
-    let mut acertos = vec![0, 0, 0, 0, 0, 0, 0, 0, 0, 0];
-    for k in 0..10 {
-        println!("{}/10", k + 1);
-        let index_inicial = k * tamanho_pasta;
-        let index_final = (k + 1) * tamanho_pasta;
+    let mut taxas = Vec::with_capacity(pastas.len());
+    for (k, (_, indices_teste)) in pastas.iter().enumerate() {
+        println!("{}/{}", k + 1, pastas.len());
+        let mut no_teste = vec![false; dados.len()];
+        for i in indices_teste {
+            no_teste[*i] = true;
+        }
+        let dados_treino: Vec<DadoPapete> = dados
+            .iter()
+            .zip(no_teste.iter())
+            .filter(|(_, teste)| !**teste)
+            .map(|(x, _)| *x)
+            .collect();
+        let dados_teste: Vec<DadoPapete> = indices_teste.iter().map(|i| dados[*i]).collect();
+
+        let mut previsor = T::calcular_de_dataset_com(&dados_treino, None, config_treino).unwrap();
+        let respostas = previsor.prever_batch(&dados_teste);
+        let acertos = respostas
+            .iter()
+            .zip(dados_teste.iter())
+            .filter(|(r, d)| Some(**r) == d.movimento)
+            .count();
+        taxas.push(acertos as f32 / dados_teste.len().max(1) as f32);
+        esperado.extend(dados_teste.iter().map(|x| x.movimento.unwrap()));
+        obtido.extend(respostas);
+    }
 
+    let mut relatorio = RelatorioAvaliacao::new(
+        MatrizConfusao::new(esperado.into_iter(), obtido.into_iter()),
+        taxas,
+    );
+    if !matches!(config_validacao.estrategia, Estrategia::Amostras) {
+        relatorio.grupos_pastas = pastas.into_iter().map(|p| p.0).collect();
+    }
+    relatorio
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PontoCurva {
+    pub amostras_por_movimento: usize,
+    //média entre sessões, sem e com transferência, sobre os mesmos dados de teste
+    pub acuracia_antes: f32,
+    pub acuracia_depois: f32,
+    pub desvio_depois: f32,
+    //(sessão, antes, depois)
+    pub por_sessao: Vec<(u32, f32, f32)>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct CurvaTransferencia {
+    pub pontos: Vec<PontoCurva>,
+}
+
+impl std::fmt::Display for CurvaTransferencia {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:>4}{:>10}{:>10}{:>10}",
+            "N", "antes", "depois", "desvio"
+        )?;
+        for p in &self.pontos {
+            writeln!(
+                f,
+                "{:>4}{:>10.4}{:>10.4}{:>10.4}",
+                p.amostras_por_movimento, p.acuracia_antes, p.acuracia_depois, p.desvio_depois
+            )?;
+            //{:#} detalha cada sessão
+            if f.alternate() {
+                for (sessao, antes, depois) in &p.por_sessao {
+                    writeln!(f, "      {:<12}{:>8.4}{:>10.4}", sessao, antes, depois)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/*
+Avalia o protocolo de calibração: para cada sessão deixada de fora, treina o modelo populacional
+com as demais sessões, chama transferir com as N primeiras amostras de cada movimento da sessão
+e mede a acurácia no restante dela. Repete para cada N de `valores_n`.
+As amostras de replay da transferência saem das sessões de treino.
+*/
+pub fn avaliar_transferencia<T: Previsor + 'static>(
+    dados: &[DadoPapete],
+    valores_n: &[usize],
+    config_treino: &ConfigTreino,
+    config_transferencia: &ConfigTransferencia,
+) -> Result<CurvaTransferencia, Box<dyn std::error::Error>> {
+    let sessoes = separar_pastas(
+        dados,
+        &ConfigValidacao {
+            estrategia: Estrategia::Sessao,
+            pastas: None,
+            seed: 0,
+        },
+    );
+
+    let mut por_n: Vec<Vec<(u32, f32, f32)>> = vec![Vec::new(); valores_n.len()];
+    for (k, (grupo, indices_sessao)) in sessoes.iter().enumerate() {
+        println!("{}/{}", k + 1, sessoes.len());
         let dados_treino: Vec<DadoPapete> = dados
             .iter()
-            .enumerate()
-            .filter(|(index, _)| index < &index_inicial || index >= &index_final)
-            .map(|(_, x)| x.clone())
+            .filter(|d| d.sessao.unwrap_or(0) != grupo[0])
+            .copied()
             .collect();
-        let dados_teste = &dados[index_inicial..index_final];
+        let mut indices_sessao = indices_sessao.clone();
+        indices_sessao.sort();
+        let sessao: Vec<DadoPapete> = indices_sessao.iter().map(|i| dados[*i]).collect();
+
+        let mut populacional: Box<dyn Previsor> = Box::new(T::calcular_de_dataset_com(
+            &dados_treino,
+            None,
+            config_treino,
+        )?);
+        if !populacional.suporta_transferencia() {
+            simple_error::bail!("Previsor não suporta transferência");
+        }
+        populacional.configurar_transferencia(config_transferencia, &dados_treino);
 
-        let mut arv = T::calcular_de_dataset(&dados_treino).unwrap();
-        for (i, resposta) in arv.prever_batch(dados_teste).iter().enumerate() {
-            obtido.push((*resposta).clone());
-            if &dados_teste[i].movimento.unwrap() == resposta {
-                acertos[k] += 1;
+        for (j, n) in valores_n.iter().enumerate() {
+            let (calibracao, teste) = separar_calibracao(&sessao, *n);
+            if calibracao.is_empty() || teste.is_empty() {
+                continue;
             }
+            let antes = populacional.acuracia(&teste);
+            let mut adaptado = match populacional.clonar() {
+                Ok(p) => p,
+                Err(_) => {
+                    let mut p: Box<dyn Previsor> = Box::new(T::calcular_de_dataset_com(
+                        &dados_treino,
+                        None,
+                        config_treino,
+                    )?);
+                    p.configurar_transferencia(config_transferencia, &dados_treino);
+                    p
+                }
+            };
+            adaptado.transferir(&calibracao);
+            por_n[j].push((grupo[0], antes, adaptado.acuracia(&teste)));
         }
     }
-    let taxa_acerto: Vec<f32> = acertos
+
+    let pontos = valores_n
         .iter()
-        .map(|a| *a as f32 / tamanho_pasta as f32)
+        .zip(por_n)
+        .filter(|(_, por_sessao)| !por_sessao.is_empty())
+        .map(|(n, por_sessao)| {
+            let antes: Vec<f32> = por_sessao.iter().map(|x| x.1).collect();
+            let depois: Vec<f32> = por_sessao.iter().map(|x| x.2).collect();
+            PontoCurva {
+                amostras_por_movimento: *n,
+                acuracia_antes: statistical::mean(&antes),
+                acuracia_depois: statistical::mean(&depois),
+                desvio_depois: if depois.len() > 1 {
+                    statistical::standard_deviation(&depois, None)
+                } else {
+                    0.0
+                },
+                por_sessao,
+            }
+        })
         .collect();
+    Ok(CurvaTransferencia { pontos })
+}
 
-    let mean = statistical::mean(&taxa_acerto);
-    let std_dev = statistical::standard_deviation(&taxa_acerto, None);
-    let min_val = taxa_acerto.clone().into_iter().reduce(f32::min).unwrap();
-    let max_val = taxa_acerto.clone().into_iter().reduce(f32::max).unwrap();
-
-    println!(
-        "taxas: {:?}\nmédia: {}\ndesvio-padrão: {}\nmín: {}\nmáx: {}\n\n{}\n",
-        taxa_acerto,
-        mean,
-        std_dev,
-        min_val,
-        max_val,
-        MatrizConfusao::new(
-            dados.iter().map(|x| x.movimento.unwrap()),
-            obtido.into_iter()
-        )
-    );
+/*
+Separa as N primeiras amostras (na ordem da sessão) de cada movimento para calibração;
+o restante fica para teste
+*/
+fn separar_calibracao(sessao: &[DadoPapete], n: usize) -> (Vec<DadoPapete>, Vec<DadoPapete>) {
+    let mut vistos = [0; 5];
+    let mut calibracao = Vec::new();
+    let mut teste = Vec::new();
+    for dado in sessao {
+        let m = dado.movimento.unwrap().as_usize();
+        if vistos[m] < n {
+            vistos[m] += 1;
+            calibracao.push(*dado);
+        } else {
+            teste.push(*dado);
+        }
+    }
+    (calibracao, teste)
 }
 
 #[allow(dead_code)]
@@ -127,3 +645,161 @@ pub fn teste_simples<T: Previsor>() {
         dados.len()
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::avaliacao::*;
+    use crate::previsor::ResultadoTransferencia;
+
+    use std::sync::Mutex;
+
+    #[test]
+    fn metricas() {
+        use Movimento::*;
+        let esperado = [Repouso, Repouso, Repouso, Flexao, Flexao, Eversao];
+        let obtido = [Repouso, Repouso, Flexao, Flexao, Flexao, Repouso];
+        let r = RelatorioAvaliacao::new(
+            MatrizConfusao::new(esperado.into_iter(), obtido.into_iter()),
+            vec![0.5, 0.7],
+        );
+        assert_eq!(r.acuracia, 4.0 / 6.0);
+        assert_eq!(r.por_classe[Repouso.as_usize()].precisao, 2.0 / 3.0);
+        assert_eq!(r.por_classe[Repouso.as_usize()].revocacao, 2.0 / 3.0);
+        assert_eq!(r.por_classe[Flexao.as_usize()].suporte, 2);
+        assert_eq!(r.acuracia_balanceada, (2.0 / 3.0 + 1.0 + 0.0) / 3.0);
+        //po = 4/6, pe = (3*3 + 2*3 + 1*0) / 36
+        let pe = 15.0 / 36.0;
+        assert!((r.kappa - (4.0 / 6.0 - pe) / (1.0 - pe)).abs() < 1e-6);
+        assert!(r.ic_acuracia.0 < r.acuracia && r.acuracia < r.ic_acuracia.1);
+        assert!(r.regressoes(&r, 0.0).is_empty());
+    }
+
+    #[test]
+    fn pastas_por_sessao() {
+        let dados: Vec<DadoPapete> = (0..30)
+            .map(|i| DadoPapete::completo(0.0, 0.0, true, Movimento::Repouso, i % 4))
+            .collect();
+        let config = ConfigValidacao {
+            estrategia: Estrategia::Sessao,
+            pastas: None,
+            seed: 3,
+        };
+        let pastas = separar_pastas(&dados, &config);
+        assert_eq!(pastas.len(), 4);
+        for (sessoes, indices) in &pastas {
+            assert_eq!(sessoes.len(), 1);
+            assert!(indices.iter().all(|i| dados[*i].sessao == Some(sessoes[0])));
+        }
+
+        let config = ConfigValidacao {
+            pastas: Some(2),
+            ..config
+        };
+        let pastas = separar_pastas(&dados, &config);
+        assert_eq!(pastas.len(), 2);
+        assert_eq!(pastas[0].1.len() + pastas[1].1.len(), 30);
+    }
+
+    //calibrações recebidas pelo PrevisorMemoria, na ordem
+    static CALIBRACOES: Mutex<Vec<Vec<DadoPapete>>> = Mutex::new(Vec::new());
+
+    //antes da transferência diz sempre repouso; depois, acerta tudo
+    struct PrevisorMemoria {
+        calibrado: bool,
+    }
+
+    impl Previsor for PrevisorMemoria {
+        fn calcular_de_dataset(_: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorMemoria { calibrado: false })
+        }
+        fn calcular_de_dataset_com(
+            _: &[DadoPapete],
+            _: Option<&[f32]>,
+            _: &ConfigTreino,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorMemoria { calibrado: false })
+        }
+        fn carregar(_: &str) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorMemoria { calibrado: false })
+        }
+        fn salvar(&self, _: &str) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+        fn prever(&mut self, entrada: DadoPapete) -> Movimento {
+            if self.calibrado {
+                entrada.movimento.unwrap()
+            } else {
+                Movimento::Repouso
+            }
+        }
+        fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
+            entrada.iter().map(|d| self.prever(*d)).collect()
+        }
+        fn transferir(&mut self, entrada: &[DadoPapete]) -> ResultadoTransferencia {
+            CALIBRACOES.lock().unwrap().push(entrada.to_vec());
+            self.calibrado = true;
+            ResultadoTransferencia {
+                acuracia_antes: 0.0,
+                acuracia_depois: 1.0,
+            }
+        }
+        fn suporta_transferencia(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn curva_de_transferencia() {
+        use Movimento::*;
+        //o pitch guarda a posição na sessão
+        let sessao_0 = (0..8).map(|i| (i, if i % 2 == 0 { Repouso } else { Flexao }, 0));
+        let sessao_1 = (0..6).map(|i| (i, if i == 1 || i == 3 { Flexao } else { Repouso }, 1));
+        let dados: Vec<DadoPapete> = sessao_0
+            .chain(sessao_1)
+            .map(|(i, movimento, sessao)| {
+                DadoPapete::completo(i as f32, 0.0, true, movimento, sessao)
+            })
+            .collect();
+
+        let curva = avaliar_transferencia::<PrevisorMemoria>(
+            &dados,
+            &[1, 3, 4],
+            &ConfigTreino::default(),
+            &ConfigTransferencia::default(),
+        )
+        .unwrap();
+
+        //as N primeiras de cada movimento, em cada sessão; com N = 4 não sobra teste
+        let mut calibracoes: Vec<(u32, Vec<f32>)> = CALIBRACOES
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|c| (c[0].sessao.unwrap(), c.iter().map(|d| d.pitch).collect()))
+            .collect();
+        calibracoes.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(
+            calibracoes,
+            vec![
+                (0, vec![0.0, 1.0]),
+                (0, vec![0.0, 1.0, 2.0, 3.0, 4.0, 5.0]),
+                (1, vec![0.0, 1.0]),
+                (1, vec![0.0, 1.0, 2.0, 3.0, 4.0]),
+            ]
+        );
+
+        assert_eq!(curva.pontos.len(), 2);
+        let mut por_sessao = curva.pontos[0].por_sessao.clone();
+        por_sessao.sort_by_key(|p| p.0);
+        //teste da sessão 0: 3 repousos e 3 flexões; da 1: 3 repousos e 1 flexão
+        assert_eq!(por_sessao, vec![(0, 0.5, 1.0), (1, 0.75, 1.0)]);
+        assert_eq!(curva.pontos[0].amostras_por_movimento, 1);
+        assert_eq!(curva.pontos[0].acuracia_antes, 0.625);
+        assert_eq!(curva.pontos[0].acuracia_depois, 1.0);
+        assert_eq!(curva.pontos[0].desvio_depois, 0.0);
+
+        let mut por_sessao = curva.pontos[1].por_sessao.clone();
+        por_sessao.sort_by_key(|p| p.0);
+        assert_eq!(curva.pontos[1].amostras_por_movimento, 3);
+        assert_eq!(por_sessao, vec![(0, 0.5, 1.0), (1, 1.0, 1.0)]);
+    }
+}
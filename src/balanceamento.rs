@@ -0,0 +1,219 @@
+/*
+Compensa o desbalanceamento entre movimentos nos dados de treino.
+Repouso costuma dominar, já que registrar também é usado para definir os offsets.
+*/
+
+use crate::dado_papete::DadoPapete;
+use crate::movimento::Movimento;
+
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+//limite de cópias de uma amostra quando o modelo não aceita pesos
+static MAX_COPIAS: usize = 100;
+
+#[derive(Clone, Debug)]
+pub enum PesoClasses {
+    Uniforme,
+    //inversamente proporcional à frequência de cada movimento
+    Balanceado,
+    //indexado por Movimento::as_usize
+    #[allow(dead_code)]
+    Manual([f32; 5]),
+}
+
+#[derive(Clone, Debug)]
+pub struct ConfigTreino {
+    pub pesos_classes: PesoClasses,
+    //repete amostras dos movimentos menos frequentes até igualar o mais frequente
+    pub reamostrar: bool,
+    pub seed: u64,
+}
+
+impl Default for ConfigTreino {
+    fn default() -> Self {
+        ConfigTreino {
+            pesos_classes: PesoClasses::Uniforme,
+            reamostrar: false,
+            seed: 0,
+        }
+    }
+}
+
+pub fn contagem_por_classe(dataset: &[DadoPapete]) -> [usize; 5] {
+    let mut contagem = [0; 5];
+    for dado in dataset {
+        if let Some(movimento) = dado.movimento {
+            contagem[movimento.as_usize()] += 1;
+        }
+    }
+    contagem
+}
+
+pub fn pesos_de_classe(dataset: &[DadoPapete], pesos: &PesoClasses) -> [f32; 5] {
+    match pesos {
+        PesoClasses::Uniforme => [1.0; 5],
+        PesoClasses::Manual(p) => *p,
+        PesoClasses::Balanceado => {
+            let contagem = contagem_por_classe(dataset);
+            let presentes = contagem.iter().filter(|c| **c > 0).count() as f32;
+            let total: usize = contagem.iter().sum();
+            contagem.map(|c| {
+                if c == 0 {
+                    0.0
+                } else {
+                    total as f32 / (presentes * c as f32)
+                }
+            })
+        }
+    }
+}
+
+/*
+Peso final de cada amostra: peso da classe vezes o peso individual, se houver
+*/
+pub fn pesos_por_amostra(
+    dataset: &[DadoPapete],
+    pesos_amostras: Option<&[f32]>,
+    config: &ConfigTreino,
+) -> Vec<f32> {
+    let pesos_classes = pesos_de_classe(dataset, &config.pesos_classes);
+    dataset
+        .iter()
+        .enumerate()
+        .map(|(i, dado)| {
+            let classe = pesos_classes[dado.movimento.unwrap().as_usize()];
+            classe * pesos_amostras.map(|p| p[i]).unwrap_or(1.0)
+        })
+        .collect()
+}
+
+/*
+Sorteia (com reposição) amostras extras de cada movimento até todos terem a mesma quantidade
+do mais frequente. Os pesos acompanham as amostras sorteadas.
+*/
+pub fn reamostrar_balanceado(
+    dataset: &[DadoPapete],
+    pesos: &[f32],
+    seed: u64,
+) -> (Vec<DadoPapete>, Vec<f32>) {
+    let contagem = contagem_por_classe(dataset);
+    let alvo = *contagem.iter().max().unwrap_or(&0);
+    let mut rng = StdRng::seed_from_u64(seed);
+
+    let mut dados = dataset.to_vec();
+    let mut novos_pesos = pesos.to_vec();
+    for movimento in Movimento::todos() {
+        let indices: Vec<usize> = (0..dataset.len())
+            .filter(|i| dataset[*i].movimento == Some(movimento))
+            .collect();
+        if indices.is_empty() {
+            continue;
+        }
+        for _ in indices.len()..alvo {
+            let i = *indices.choose(&mut rng).unwrap();
+            dados.push(dataset[i]);
+            novos_pesos.push(pesos[i]);
+        }
+    }
+    (dados, novos_pesos)
+}
+
+/*
+Aplica a configuração: reamostra se pedido e calcula o peso de cada amostra resultante
+*/
+pub fn preparar(
+    dataset: &[DadoPapete],
+    pesos_amostras: Option<&[f32]>,
+    config: &ConfigTreino,
+) -> (Vec<DadoPapete>, Vec<f32>) {
+    let individuais: Vec<f32> = match pesos_amostras {
+        Some(p) => p.to_vec(),
+        None => vec![1.0; dataset.len()],
+    };
+    let (dados, individuais) = if config.reamostrar {
+        reamostrar_balanceado(dataset, &individuais, config.seed)
+    } else {
+        (dataset.to_vec(), individuais)
+    };
+    let pesos = pesos_por_amostra(&dados, Some(&individuais), config);
+    (dados, pesos)
+}
+
+/*
+Para modelos que não aceitam pesos: repete cada amostra proporcionalmente ao seu peso,
+tomando o menor peso positivo como uma cópia. Amostras de peso zero são descartadas.
+*/
+pub fn replicar_por_peso<T: Clone>(dataset: &[T], pesos: &[f32]) -> Vec<T> {
+    let menor = pesos
+        .iter()
+        .copied()
+        .filter(|p| *p > 0.0)
+        .reduce(f32::min)
+        .unwrap_or(1.0);
+    let mut saida = Vec::with_capacity(dataset.len());
+    for (dado, peso) in dataset.iter().zip(pesos.iter()) {
+        if *peso > 0.0 {
+            let copias = ((peso / menor).round() as usize).clamp(1, MAX_COPIAS);
+            saida.extend(std::iter::repeat_n(dado.clone(), copias));
+        }
+    }
+    saida
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::balanceamento::*;
+    use crate::dado_papete::DadoPapete;
+    use crate::movimento::Movimento;
+
+    fn dados() -> Vec<DadoPapete> {
+        let mut d = vec![DadoPapete::completo(0.0, 0.0, true, Movimento::Repouso, 0); 6];
+        d.extend(vec![
+            DadoPapete::completo(
+                0.5,
+                0.0,
+                true,
+                Movimento::Dorsiflexao,
+                0
+            );
+            2
+        ]);
+        d
+    }
+
+    #[test]
+    fn pesos_balanceados() {
+        let pesos = pesos_de_classe(&dados(), &PesoClasses::Balanceado);
+        assert_eq!(pesos[Movimento::Repouso.as_usize()], 8.0 / 12.0);
+        assert_eq!(pesos[Movimento::Dorsiflexao.as_usize()], 2.0);
+        assert_eq!(pesos[Movimento::Flexao.as_usize()], 0.0);
+
+        let replicados = replicar_por_peso(
+            &dados(),
+            &pesos_por_amostra(
+                &dados(),
+                None,
+                &ConfigTreino {
+                    pesos_classes: PesoClasses::Balanceado,
+                    ..Default::default()
+                },
+            ),
+        );
+        assert_eq!(contagem_por_classe(&replicados), [6, 0, 6, 0, 0]);
+    }
+
+    #[test]
+    fn reamostragem() {
+        let config = ConfigTreino {
+            reamostrar: true,
+            seed: 7,
+            ..Default::default()
+        };
+        let (a, pesos) = preparar(&dados(), None, &config);
+        assert_eq!(contagem_por_classe(&a), [6, 0, 6, 0, 0]);
+        assert_eq!(pesos.len(), a.len());
+
+        let (b, _) = preparar(&dados(), None, &config);
+        assert!(a.iter().zip(b.iter()).all(|(x, y)| x.pitch == y.pitch));
+    }
+}
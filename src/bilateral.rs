@@ -0,0 +1,107 @@
+/*
+Classificação dos dois pés ao mesmo tempo: cada pé é previsto separadamente e o par vira um gesto,
+para o jogo saber qual pé fez o quê e reconhecer gestos que só existem com os dois pés.
+*/
+
+use crate::movimento::Movimento;
+
+use std::fmt;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GestoBilateral {
+    //nenhum pé com previsão (desconectado ou ainda sem offset)
+    SemDados,
+    Repouso,
+    //só um pé fora do repouso
+    Esquerdo,
+    Direito,
+    //os dois pés no mesmo movimento, como dorsiflexão bilateral
+    Ambos,
+    //os dois pés fora do repouso, em movimentos diferentes
+    Diferentes,
+}
+
+/*
+Resultado para o jogo. tem_esq e tem_dir dizem se o movimento do pé correspondente é válido.
+movimento é o rótulo combinado: o do pé que se mexeu, o comum em Ambos ou Repouso.
+*/
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MovimentoBilateral {
+    pub tem_esq: bool,
+    pub esq: Movimento,
+    pub tem_dir: bool,
+    pub dir: Movimento,
+    pub gesto: GestoBilateral,
+    pub movimento: Movimento,
+}
+
+impl MovimentoBilateral {
+    pub fn esq(&self) -> Option<Movimento> {
+        self.tem_esq.then_some(self.esq)
+    }
+
+    pub fn dir(&self) -> Option<Movimento> {
+        self.tem_dir.then_some(self.dir)
+    }
+}
+
+pub fn combinar(esq: Option<Movimento>, dir: Option<Movimento>) -> MovimentoBilateral {
+    let movendo = |m: Option<Movimento>| m.filter(|m| *m != Movimento::Repouso);
+    let (gesto, movimento) = match (movendo(esq), movendo(dir)) {
+        _ if esq.is_none() && dir.is_none() => (GestoBilateral::SemDados, Movimento::Repouso),
+        (None, None) => (GestoBilateral::Repouso, Movimento::Repouso),
+        (Some(m), None) => (GestoBilateral::Esquerdo, m),
+        (None, Some(m)) => (GestoBilateral::Direito, m),
+        (Some(a), Some(b)) if a == b => (GestoBilateral::Ambos, a),
+        (Some(_), Some(_)) => (GestoBilateral::Diferentes, Movimento::Repouso),
+    };
+    MovimentoBilateral {
+        tem_esq: esq.is_some(),
+        esq: esq.unwrap_or(Movimento::Repouso),
+        tem_dir: dir.is_some(),
+        dir: dir.unwrap_or(Movimento::Repouso),
+        gesto,
+        movimento,
+    }
+}
+
+impl fmt::Display for MovimentoBilateral {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let pe = |m: Option<Movimento>| m.map(|m| m.to_string()).unwrap_or_else(|| "-".to_string());
+        write!(
+            f,
+            "E: {}\tD: {}\t{:?} ({})",
+            pe(self.esq()),
+            pe(self.dir()),
+            self.gesto,
+            self.movimento
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bilateral::*;
+
+    #[test]
+    fn gestos() {
+        use Movimento::*;
+        assert_eq!(combinar(None, None).gesto, GestoBilateral::SemDados);
+        assert_eq!(combinar(Some(Repouso), None).gesto, GestoBilateral::Repouso);
+
+        let r = combinar(Some(Repouso), Some(Flexao));
+        assert_eq!((r.gesto, r.movimento), (GestoBilateral::Direito, Flexao));
+        assert_eq!(r.esq(), Some(Repouso));
+
+        let r = combinar(Some(Dorsiflexao), Some(Dorsiflexao));
+        assert_eq!((r.gesto, r.movimento), (GestoBilateral::Ambos, Dorsiflexao));
+
+        let r = combinar(Some(Eversao), Some(Inversao));
+        assert_eq!(r.gesto, GestoBilateral::Diferentes);
+
+        let r = combinar(Some(Inversao), None);
+        assert_eq!((r.gesto, r.dir()), (GestoBilateral::Esquerdo, None));
+    }
+}
@@ -0,0 +1,608 @@
+/*
+Calibração guiada, igual para todos os jogos:
+1. espera as duas papetes;
+2. captura o repouso por alguns segundos, repetindo se os pés se mexerem;
+3. pede cada movimento;
+4. valida a amplitude e a direção alcançadas, pedindo de novo se ficaram fora do esperado;
+5. termina com os offsets e as amostras de calibração.
+A máquina de estados só recebe amostras e o instante atual; quem lê os sensores é a Papete.
+*/
+
+use crate::{
+    arco::componente,
+    dado_papete::DadoPapete,
+    lado::{Lado, PorLado},
+    movimento::Movimento,
+};
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EtapaCalibracao {
+    Inativa,
+    AguardandoPes,
+    Repouso,
+    Movimento,
+    Concluida,
+    Cancelada,
+}
+
+/*
+Ângulos em radianos.
+- desvio_max_repouso: desvio-padrão máximo do pitch e do roll de cada pé durante o repouso;
+- amplitude_min: distância do repouso que cada pé precisa alcançar em cada movimento;
+- angulo_max_direcao: ângulo máximo entre a direção média de cada pé perto do pico e a do
+  movimento pedido;
+- tentativas: depois delas o movimento é aceito mesmo abaixo da amplitude mínima. Se a direção
+  continuar errada, ele é pulado sem amostras.
+*/
+#[derive(Clone, Debug)]
+pub struct ConfigCalibracao {
+    pub tempo_repouso: Duration,
+    pub desvio_max_repouso: f32,
+    pub tempo_movimento: Duration,
+    pub amplitude_min: f32,
+    pub angulo_max_direcao: f32,
+    pub tentativas: usize,
+    pub movimentos: Vec<Movimento>,
+}
+
+impl Default for ConfigCalibracao {
+    fn default() -> Self {
+        ConfigCalibracao {
+            tempo_repouso: Duration::from_secs(3),
+            desvio_max_repouso: 0.02,
+            tempo_movimento: Duration::from_secs(4),
+            amplitude_min: 0.15,
+            angulo_max_direcao: std::f32::consts::FRAC_PI_4,
+            tentativas: 3,
+            movimentos: vec![
+                Movimento::Dorsiflexao,
+                Movimento::Flexao,
+                Movimento::Eversao,
+                Movimento::Inversao,
+            ],
+        }
+    }
+}
+
+pub struct Calibracao {
+    config: ConfigCalibracao,
+    etapa: EtapaCalibracao,
+    //etapa interrompida quando uma papete desconecta
+    pausada: Option<EtapaCalibracao>,
+    indice_movimento: usize,
+    tentativa: usize,
+    inicio_janela: Option<Instant>,
+    ultima_atualizacao: Option<Instant>,
+    janela: Vec<DadoPapete>,
+    offsets: PorLado<Option<DadoPapete>>,
+    amostras: Vec<DadoPapete>,
+    //pico de cada pé, indexado por Movimento::as_usize
+    amplitudes: PorLado<[f32; 5]>,
+    abaixo_minimo: Vec<Movimento>,
+    //motivo da última janela descartada, para a instrução
+    instavel: bool,
+    insuficiente: bool,
+    direcao_errada: bool,
+}
+
+impl Calibracao {
+    pub fn new(config: ConfigCalibracao) -> Calibracao {
+        Calibracao {
+            config,
+            etapa: EtapaCalibracao::AguardandoPes,
+            pausada: None,
+            indice_movimento: 0,
+            tentativa: 0,
+            inicio_janela: None,
+            ultima_atualizacao: None,
+            janela: Vec::new(),
+            offsets: PorLado::default(),
+            amostras: Vec::new(),
+            amplitudes: PorLado::default(),
+            abaixo_minimo: Vec::new(),
+            instavel: false,
+            insuficiente: false,
+            direcao_errada: false,
+        }
+    }
+
+    /*
+    Recebe as leituras atuais (sem offset) e avança a calibração quando a janela da etapa termina
+    */
+    pub fn atualizar(
+        &mut self,
+        dados: &PorLado<Option<DadoPapete>>,
+        agora: Instant,
+    ) -> EtapaCalibracao {
+        if !self.ativa() {
+            return self.etapa;
+        }
+        self.ultima_atualizacao = Some(agora);
+
+        let (esq, dir) = match (dados.esq, dados.dir) {
+            (Some(esq), Some(dir)) => (esq, dir),
+            _ => {
+                if self.etapa != EtapaCalibracao::AguardandoPes {
+                    self.pausada = Some(self.etapa);
+                    self.etapa = EtapaCalibracao::AguardandoPes;
+                    self.reiniciar_janela();
+                }
+                return self.etapa;
+            }
+        };
+        if self.etapa == EtapaCalibracao::AguardandoPes {
+            self.etapa = self.pausada.take().unwrap_or(EtapaCalibracao::Repouso);
+        }
+
+        let inicio = *self.inicio_janela.get_or_insert(agora);
+        self.janela.push(esq);
+        self.janela.push(dir);
+        if agora.duration_since(inicio) < self.duracao_etapa() {
+            return self.etapa;
+        }
+        match self.etapa {
+            EtapaCalibracao::Repouso => self.concluir_repouso(),
+            EtapaCalibracao::Movimento => self.concluir_movimento(),
+            _ => {}
+        }
+        self.reiniciar_janela();
+        self.etapa
+    }
+
+    pub fn cancelar(&mut self) {
+        if self.ativa() {
+            self.etapa = EtapaCalibracao::Cancelada;
+        }
+    }
+
+    pub fn etapa(&self) -> EtapaCalibracao {
+        self.etapa
+    }
+
+    /*
+    Movimento que o paciente deve fazer agora, se houver
+    */
+    pub fn movimento_atual(&self) -> Option<Movimento> {
+        match self.pausada.unwrap_or(self.etapa) {
+            EtapaCalibracao::Repouso => Some(Movimento::Repouso),
+            EtapaCalibracao::Movimento => Some(self.config.movimentos[self.indice_movimento]),
+            _ => None,
+        }
+    }
+
+    /*
+    De 0 a 1: conectar, repouso e cada movimento contam como uma etapa
+    */
+    pub fn progresso(&self) -> f32 {
+        let total = (self.config.movimentos.len() + 2) as f32;
+        let feitas = match self.pausada.unwrap_or(self.etapa) {
+            EtapaCalibracao::Inativa | EtapaCalibracao::Cancelada => return 0.0,
+            EtapaCalibracao::Concluida => return 1.0,
+            EtapaCalibracao::AguardandoPes => 0,
+            EtapaCalibracao::Repouso => 1,
+            EtapaCalibracao::Movimento => 2 + self.indice_movimento,
+        };
+        let fracao = match (self.inicio_janela, self.ultima_atualizacao) {
+            (Some(inicio), Some(agora)) => (agora.duration_since(inicio).as_secs_f32()
+                / self.duracao_etapa().as_secs_f32().max(f32::EPSILON))
+            .min(1.0),
+            _ => 0.0,
+        };
+        (feitas as f32 + fracao) / total
+    }
+
+    pub fn instrucao(&self) -> String {
+        match self.etapa {
+            EtapaCalibracao::Inativa => "Calibração não iniciada".to_string(),
+            EtapaCalibracao::AguardandoPes => "Conecte as duas papetes".to_string(),
+            EtapaCalibracao::Repouso => format!(
+                "{}{}: {}",
+                if self.instavel {
+                    "Os pés se mexeram, vamos repetir. "
+                } else {
+                    ""
+                },
+                Movimento::Repouso.str_completa(),
+                Movimento::Repouso.descricao()
+            ),
+            EtapaCalibracao::Movimento => {
+                let movimento = self.config.movimentos[self.indice_movimento];
+                format!(
+                    "{}{}: {}",
+                    if self.direcao_errada {
+                        "Movimento na direção errada, tente de novo. "
+                    } else if self.insuficiente {
+                        "Amplitude insuficiente, tente de novo. "
+                    } else {
+                        ""
+                    },
+                    movimento.str_completa(),
+                    movimento.descricao()
+                )
+            }
+            EtapaCalibracao::Concluida => "Calibração concluída".to_string(),
+            EtapaCalibracao::Cancelada => "Calibração cancelada".to_string(),
+        }
+    }
+
+    /*
+    Offset de repouso do pé, disponível depois da etapa de repouso
+    */
+    pub fn offset(&self, lado: Lado) -> Option<DadoPapete> {
+        self.offsets[lado]
+    }
+
+    /*
+    Amostras rotuladas e com o offset descontado, prontas para o retreino
+    */
+    pub fn amostras(&self) -> &[DadoPapete] {
+        &self.amostras
+    }
+
+    /*
+    Amplitude (rad) alcançada no movimento: o menor pico entre os dois pés
+    */
+    #[allow(dead_code)]
+    pub fn amplitude(&self, movimento: Movimento) -> f32 {
+        self.amplitudes.esq[movimento.as_usize()].min(self.amplitudes.dir[movimento.as_usize()])
+    }
+
+    pub fn amplitudes_pe(&self, lado: Lado) -> [f32; 5] {
+        self.amplitudes[lado]
+    }
+
+    /*
+    Movimentos aceitos depois de esgotar as tentativas sem alcançar a amplitude mínima,
+    ou pulados por continuarem na direção errada
+    */
+    pub fn abaixo_minimo(&self) -> &[Movimento] {
+        &self.abaixo_minimo
+    }
+
+    fn ativa(&self) -> bool {
+        !matches!(
+            self.etapa,
+            EtapaCalibracao::Inativa | EtapaCalibracao::Concluida | EtapaCalibracao::Cancelada
+        )
+    }
+
+    fn duracao_etapa(&self) -> Duration {
+        match self.pausada.unwrap_or(self.etapa) {
+            EtapaCalibracao::Repouso => self.config.tempo_repouso,
+            _ => self.config.tempo_movimento,
+        }
+    }
+
+    fn reiniciar_janela(&mut self) {
+        self.inicio_janela = None;
+        self.janela.clear();
+    }
+
+    fn concluir_repouso(&mut self) {
+        let mut offsets = PorLado::default();
+        for lado in Lado::todos() {
+            let amostras: Vec<DadoPapete> = self
+                .janela
+                .iter()
+                .filter(|d| d.lado() == lado)
+                .copied()
+                .collect();
+            let (media, desvio) = media_desvio(&amostras);
+            if desvio.0 > self.config.desvio_max_repouso
+                || desvio.1 > self.config.desvio_max_repouso
+            {
+                self.instavel = true;
+                return;
+            }
+            offsets[lado] = Some(media);
+        }
+        self.instavel = false;
+        self.offsets = offsets;
+        self.amostras = self.corrigir(Movimento::Repouso);
+        self.etapa = if self.config.movimentos.is_empty() {
+            EtapaCalibracao::Concluida
+        } else {
+            EtapaCalibracao::Movimento
+        };
+    }
+
+    fn concluir_movimento(&mut self) {
+        let movimento = self.config.movimentos[self.indice_movimento];
+        let corrigidos = self.corrigir(movimento);
+
+        let mut picos = PorLado::new(0.0, 0.0);
+        let mut selecionadas: Vec<DadoPapete> = Vec::with_capacity(corrigidos.len());
+        let mut direcao_errada = false;
+        for lado in Lado::todos() {
+            let amostras = corrigidos.iter().filter(|d| d.lado() == lado);
+            let pico = amostras.clone().map(distancia).fold(0.0, f32::max);
+            picos[lado] = pico;
+            //só as amostras perto do pico, descartando o tempo de reação e a volta
+            let perto_pico: Vec<DadoPapete> = amostras
+                .filter(|d| distancia(d) >= 0.5 * pico)
+                .copied()
+                .collect();
+            //o cosseno entre a direção média e a do movimento, nos sinais de arco::componente
+            let (media, _) = media_desvio(&perto_pico);
+            direcao_errada |= movimento != Movimento::Repouso
+                && componente(movimento, &media)
+                    < self.config.angulo_max_direcao.cos() * distancia(&media);
+            selecionadas.extend(perto_pico);
+        }
+
+        let insuficiente = picos.esq.min(picos.dir) < self.config.amplitude_min;
+        if insuficiente || direcao_errada {
+            self.tentativa += 1;
+            if self.tentativa < self.config.tentativas {
+                self.insuficiente = insuficiente;
+                self.direcao_errada = direcao_errada;
+                return;
+            }
+            self.abaixo_minimo.push(movimento);
+        }
+        //amostras de outro movimento não podem ir rotuladas com este
+        if !direcao_errada {
+            for (lado, amplitudes) in self.amplitudes.iter_mut() {
+                amplitudes[movimento.as_usize()] = picos[lado];
+            }
+            self.amostras.extend(selecionadas);
+        }
+        self.tentativa = 0;
+        self.insuficiente = false;
+        self.direcao_errada = false;
+        self.indice_movimento += 1;
+        if self.indice_movimento == self.config.movimentos.len() {
+            self.etapa = EtapaCalibracao::Concluida;
+        }
+    }
+
+    //janela atual com o offset de cada pé descontado e rotulada com o movimento
+    fn corrigir(&self, movimento: Movimento) -> Vec<DadoPapete> {
+        self.janela
+            .iter()
+            .map(|d| {
+                let mut d = *d;
+                d -= self.offsets[d.lado()].unwrap();
+                d.movimento = Some(movimento);
+                d
+            })
+            .collect()
+    }
+}
+
+/*
+Qualidade da última janela de repouso de um pé: quantas amostras tinha,
+o desvio-padrão (rad) do pitch e do roll e se foi aceita como offset
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QualidadeOffset {
+    pub amostras: u32,
+    pub desvio_pitch: f32,
+    pub desvio_roll: f32,
+    pub estavel: bool,
+}
+
+/*
+Offset de repouso fora da calibração guiada: média de uma janela deslizante de amostras de um pé,
+aceita só quando o desvio-padrão fica abaixo do limite
+*/
+#[derive(Clone, Debug)]
+pub struct CapturaOffset {
+    pub tamanho_janela: usize,
+    pub desvio_max: f32,
+    janela: VecDeque<DadoPapete>,
+    qualidade: QualidadeOffset,
+}
+
+impl Default for CapturaOffset {
+    fn default() -> Self {
+        CapturaOffset::new(10, 0.02)
+    }
+}
+
+impl CapturaOffset {
+    pub fn new(tamanho_janela: usize, desvio_max: f32) -> CapturaOffset {
+        CapturaOffset {
+            tamanho_janela: tamanho_janela.max(1),
+            desvio_max,
+            janela: VecDeque::with_capacity(tamanho_janela),
+            qualidade: QualidadeOffset::default(),
+        }
+    }
+
+    /*
+    Retorna o offset quando a janela está cheia e estável; depois disso a captura recomeça
+    */
+    pub fn adicionar(&mut self, dado: DadoPapete) -> Option<DadoPapete> {
+        if self.janela.len() == self.tamanho_janela {
+            self.janela.pop_front();
+        }
+        self.janela.push_back(dado);
+
+        let amostras: Vec<DadoPapete> = self.janela.iter().copied().collect();
+        let (media, desvio) = media_desvio(&amostras);
+        let cheia = amostras.len() == self.tamanho_janela;
+        self.qualidade = QualidadeOffset {
+            amostras: amostras.len() as u32,
+            desvio_pitch: desvio.0,
+            desvio_roll: desvio.1,
+            estavel: cheia && desvio.0 <= self.desvio_max && desvio.1 <= self.desvio_max,
+        };
+        if self.qualidade.estavel {
+            self.janela.clear();
+            Some(media)
+        } else {
+            None
+        }
+    }
+
+    pub fn reiniciar(&mut self) {
+        self.janela.clear();
+        self.qualidade = QualidadeOffset::default();
+    }
+
+    pub fn qualidade(&self) -> QualidadeOffset {
+        self.qualidade
+    }
+}
+
+/*
+Média e desvio-padrão (pitch, roll) das amostras, que devem ser todas do mesmo pé
+*/
+pub fn media_desvio(amostras: &[DadoPapete]) -> (DadoPapete, (f32, f32)) {
+    let n = amostras.len().max(1) as f32;
+    let lado_esq = amostras.first().map(|d| d.lado_esq).unwrap_or(false);
+    let pitch = amostras.iter().map(|d| d.pitch).sum::<f32>() / n;
+    let roll = amostras.iter().map(|d| d.roll).sum::<f32>() / n;
+    let var_pitch = amostras
+        .iter()
+        .map(|d| (d.pitch - pitch).powi(2))
+        .sum::<f32>()
+        / n;
+    let var_roll = amostras
+        .iter()
+        .map(|d| (d.roll - roll).powi(2))
+        .sum::<f32>()
+        / n;
+    (
+        DadoPapete::basico(pitch, roll, lado_esq),
+        (var_pitch.sqrt(), var_roll.sqrt()),
+    )
+}
+
+/*
+Distância do dado corrigido ao repouso; é a amplitude usada na calibração e nas repetições
+*/
+pub fn distancia(dado: &DadoPapete) -> f32 {
+    (dado.pitch * dado.pitch + dado.roll * dado.roll).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::calibracao::*;
+
+    fn pes(pitch: f32, roll: f32) -> PorLado<Option<DadoPapete>> {
+        PorLado::new(
+            Some(DadoPapete::basico(0.1 + pitch, -0.2 + roll, true)),
+            Some(DadoPapete::basico(-0.1 + pitch, 0.3 + roll, false)),
+        )
+    }
+
+    #[test]
+    fn fluxo_completo() {
+        let config = ConfigCalibracao {
+            movimentos: vec![Movimento::Dorsiflexao],
+            tentativas: 2,
+            ..Default::default()
+        };
+        let mut c = Calibracao::new(config);
+        let mut agora = Instant::now();
+        let passo = Duration::from_millis(100);
+        let mut rodar = |c: &mut Calibracao, dados: &PorLado<Option<DadoPapete>>, segundos: u32| {
+            for _ in 0..segundos * 10 + 1 {
+                agora += passo;
+                c.atualizar(dados, agora);
+            }
+        };
+
+        let so_esq = PorLado {
+            dir: None,
+            ..pes(0.0, 0.0)
+        };
+        rodar(&mut c, &so_esq, 1);
+        assert_eq!(c.etapa(), EtapaCalibracao::AguardandoPes);
+
+        rodar(&mut c, &pes(0.0, 0.0), 3);
+        assert_eq!(c.etapa(), EtapaCalibracao::Movimento);
+        assert_eq!(c.movimento_atual(), Some(Movimento::Dorsiflexao));
+        assert!((c.offset(Lado::Esquerdo).unwrap().roll + 0.2).abs() < 1e-6);
+
+        //amplitude abaixo do mínimo: repete
+        rodar(&mut c, &pes(-0.05, 0.0), 4);
+        assert_eq!(c.etapa(), EtapaCalibracao::Movimento);
+        assert!(c.instrucao().starts_with("Amplitude insuficiente"));
+
+        //dorsiflexão tem pitch negativo
+        rodar(&mut c, &pes(-0.4, 0.0), 4);
+        assert_eq!(c.etapa(), EtapaCalibracao::Concluida);
+        assert!(c.abaixo_minimo().is_empty());
+        assert!((c.amplitude(Movimento::Dorsiflexao) - 0.4).abs() < 1e-5);
+        assert!(c
+            .amostras()
+            .iter()
+            .any(|d| d.movimento == Some(Movimento::Dorsiflexao) && (d.pitch + 0.4).abs() < 1e-5));
+    }
+
+    #[test]
+    fn direcao_do_movimento() {
+        let config = ConfigCalibracao {
+            movimentos: vec![Movimento::Eversao, Movimento::Flexao],
+            tentativas: 2,
+            ..Default::default()
+        };
+        let mut c = Calibracao::new(config);
+        let mut agora = Instant::now();
+        let mut rodar = |c: &mut Calibracao, dados: &PorLado<Option<DadoPapete>>, segundos: u32| {
+            for _ in 0..segundos * 10 + 1 {
+                agora += Duration::from_millis(100);
+                c.atualizar(dados, agora);
+            }
+        };
+        //roll positivo é eversão no pé esquerdo e inversão no direito
+        let pes_roll = |esq: f32, dir: f32| {
+            PorLado::new(
+                Some(DadoPapete::basico(0.0, esq, true)),
+                Some(DadoPapete::basico(0.0, dir, false)),
+            )
+        };
+        rodar(&mut c, &pes_roll(0.0, 0.0), 3);
+        assert_eq!(c.movimento_atual(), Some(Movimento::Eversao));
+
+        //os dois pés para o mesmo lado: o direito fez inversão
+        rodar(&mut c, &pes_roll(0.4, 0.4), 4);
+        assert_eq!(c.movimento_atual(), Some(Movimento::Eversao));
+        assert!(c.instrucao().starts_with("Movimento na direção errada"));
+
+        rodar(&mut c, &pes_roll(0.4, -0.4), 4);
+        assert_eq!(c.movimento_atual(), Some(Movimento::Flexao));
+        assert!((c.amplitude(Movimento::Eversao) - 0.4).abs() < 1e-5);
+
+        //dorsiflexão no lugar da flexão plantar, até esgotar as tentativas: pulada sem amostras
+        let amostras = c.amostras().len();
+        rodar(&mut c, &pes(-0.4, 0.0), 4);
+        assert!(c.instrucao().starts_with("Movimento na direção errada"));
+        rodar(&mut c, &pes(-0.4, 0.0), 4);
+        assert_eq!(c.etapa(), EtapaCalibracao::Concluida);
+        assert_eq!(c.abaixo_minimo(), &[Movimento::Flexao]);
+        assert_eq!(c.amostras().len(), amostras);
+        assert_eq!(c.amplitude(Movimento::Flexao), 0.0);
+    }
+
+    #[test]
+    fn offset_estavel() {
+        let mut captura = CapturaOffset::new(4, 0.02);
+        //pé se mexendo: janela rejeitada
+        for pitch in [0.0, 0.3, 0.0, 0.3] {
+            assert!(captura
+                .adicionar(DadoPapete::basico(pitch, 0.1, true))
+                .is_none());
+        }
+        assert!(!captura.qualidade().estavel);
+        assert!(captura.qualidade().desvio_pitch > 0.1);
+
+        let mut offset = None;
+        for pitch in [0.2, 0.21, 0.2, 0.21] {
+            offset = captura.adicionar(DadoPapete::basico(pitch, 0.1, true));
+        }
+        let offset = offset.unwrap();
+        assert!((offset.pitch - 0.205).abs() < 1e-6 && offset.lado_esq);
+        assert_eq!(captura.qualidade().amostras, 4);
+    }
+}
@@ -0,0 +1,357 @@
+/*
+Características de uma janela de tempo sobre o fluxo de amostras de um pé, para previsores que
+reconhecem o movimento enquanto ele acontece e não só a postura mantida.
+
+Pitch, roll e lado são normalizados como em DadoPapete::array_normalizado, para que a
+especificação instantânea dê exatamente a entrada dos modelos atuais. As demais ficam em
+unidades físicas: rad, rad/s e s.
+*/
+
+use crate::{calibracao::distancia, dado_papete::DadoPapete, lado::PorLado};
+
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+//intervalo assumido entre as amostras gravadas sem instante_ms
+pub const PERIODO_COLETA: Duration = Duration::from_millis(80);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Caracteristica {
+    Pitch,
+    Roll,
+    Lado,
+    //variação desde a amostra anterior
+    DeltaPitch,
+    DeltaRoll,
+    //variação média por segundo ao longo da janela
+    VelocidadePitch,
+    VelocidadeRoll,
+    MinPitch,
+    MaxPitch,
+    MediaPitch,
+    DesvioPitch,
+    MinRoll,
+    MaxRoll,
+    MediaRoll,
+    DesvioRoll,
+    //desde a última amostra perto do repouso, ou desde a primeira se ainda não houve
+    TempoDesdeRepouso,
+}
+
+impl Caracteristica {
+    pub fn todas() -> [Caracteristica; 16] {
+        use Caracteristica::*;
+        [
+            Pitch,
+            Roll,
+            Lado,
+            DeltaPitch,
+            DeltaRoll,
+            VelocidadePitch,
+            VelocidadeRoll,
+            MinPitch,
+            MaxPitch,
+            MediaPitch,
+            DesvioPitch,
+            MinRoll,
+            MaxRoll,
+            MediaRoll,
+            DesvioRoll,
+            TempoDesdeRepouso,
+        ]
+    }
+}
+
+/*
+O que um previsor espera receber, na ordem do vetor.
+- janela_ms: duração da janela das estatísticas e da velocidade;
+- limiar_repouso: distância do repouso (rad) abaixo da qual o pé conta como em repouso.
+*/
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EspecCaracteristicas {
+    pub caracteristicas: Vec<Caracteristica>,
+    pub janela_ms: u64,
+    pub limiar_repouso: f32,
+}
+
+impl Default for EspecCaracteristicas {
+    fn default() -> Self {
+        EspecCaracteristicas::instantanea()
+    }
+}
+
+impl EspecCaracteristicas {
+    /*
+    Pitch, roll e lado, a entrada dos modelos sem janela
+    */
+    pub fn instantanea() -> EspecCaracteristicas {
+        EspecCaracteristicas {
+            caracteristicas: vec![
+                Caracteristica::Pitch,
+                Caracteristica::Roll,
+                Caracteristica::Lado,
+            ],
+            janela_ms: 0,
+            limiar_repouso: 0.1,
+        }
+    }
+
+    /*
+    Todas as características, com a janela dada
+    */
+    pub fn dinamica(janela_ms: u64) -> EspecCaracteristicas {
+        EspecCaracteristicas {
+            caracteristicas: Caracteristica::todas().to_vec(),
+            janela_ms,
+            ..EspecCaracteristicas::instantanea()
+        }
+    }
+
+    pub fn tamanho(&self) -> usize {
+        self.caracteristicas.len()
+    }
+}
+
+/*
+Janela deslizante de um pé. Recebe os dados corrigidos (sem offset), na ordem em que chegam.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct JanelaCaracteristicas {
+    espec: EspecCaracteristicas,
+    amostras: VecDeque<(Instant, f32, f32)>,
+    ultimo_repouso: Option<Instant>,
+    inicio: Option<Instant>,
+}
+
+impl JanelaCaracteristicas {
+    pub fn new(espec: EspecCaracteristicas) -> JanelaCaracteristicas {
+        JanelaCaracteristicas {
+            espec,
+            ..Default::default()
+        }
+    }
+
+    pub fn espec(&self) -> &EspecCaracteristicas {
+        &self.espec
+    }
+
+    pub fn limpar(&mut self) {
+        *self = JanelaCaracteristicas::new(self.espec.clone());
+    }
+
+    /*
+    Adiciona o dado e retorna o vetor da especificação
+    */
+    pub fn atualizar(&mut self, dado: &DadoPapete, agora: Instant) -> Vec<f32> {
+        let anterior = self.amostras.back().map(|&(_, pitch, roll)| (pitch, roll));
+        self.amostras.push_back((agora, dado.pitch, dado.roll));
+        let janela = Duration::from_millis(self.espec.janela_ms);
+        while let Some(&(instante, _, _)) = self.amostras.front() {
+            if self.amostras.len() > 1 && agora.duration_since(instante) > janela {
+                self.amostras.pop_front();
+            } else {
+                break;
+            }
+        }
+        let inicio = *self.inicio.get_or_insert(agora);
+        if distancia(dado) <= self.espec.limiar_repouso {
+            self.ultimo_repouso = Some(agora);
+        }
+
+        let normalizado = dado.array_normalizado();
+        let (delta_pitch, delta_roll) = anterior
+            .map(|(pitch, roll)| (dado.pitch - pitch, dado.roll - roll))
+            .unwrap_or((0.0, 0.0));
+        let (velocidade_pitch, velocidade_roll) = self.velocidades();
+        let pitch = Estatisticas::de(self.amostras.iter().map(|a| a.1));
+        let roll = Estatisticas::de(self.amostras.iter().map(|a| a.2));
+        let desde_repouso = agora
+            .duration_since(self.ultimo_repouso.unwrap_or(inicio))
+            .as_secs_f32();
+
+        self.espec
+            .caracteristicas
+            .iter()
+            .map(|c| match c {
+                Caracteristica::Pitch => normalizado[0],
+                Caracteristica::Roll => normalizado[1],
+                Caracteristica::Lado => normalizado[2],
+                Caracteristica::DeltaPitch => delta_pitch,
+                Caracteristica::DeltaRoll => delta_roll,
+                Caracteristica::VelocidadePitch => velocidade_pitch,
+                Caracteristica::VelocidadeRoll => velocidade_roll,
+                Caracteristica::MinPitch => pitch.min,
+                Caracteristica::MaxPitch => pitch.max,
+                Caracteristica::MediaPitch => pitch.media,
+                Caracteristica::DesvioPitch => pitch.desvio,
+                Caracteristica::MinRoll => roll.min,
+                Caracteristica::MaxRoll => roll.max,
+                Caracteristica::MediaRoll => roll.media,
+                Caracteristica::DesvioRoll => roll.desvio,
+                Caracteristica::TempoDesdeRepouso => desde_repouso,
+            })
+            .collect()
+    }
+
+    fn velocidades(&self) -> (f32, f32) {
+        match (self.amostras.front(), self.amostras.back()) {
+            (Some(primeira), Some(ultima)) if ultima.0 > primeira.0 => {
+                let dt = ultima.0.duration_since(primeira.0).as_secs_f32();
+                ((ultima.1 - primeira.1) / dt, (ultima.2 - primeira.2) / dt)
+            }
+            _ => (0.0, 0.0),
+        }
+    }
+}
+
+struct Estatisticas {
+    min: f32,
+    max: f32,
+    media: f32,
+    desvio: f32,
+}
+
+impl Estatisticas {
+    fn de(valores: impl Iterator<Item = f32> + Clone) -> Estatisticas {
+        let n = valores.clone().count().max(1) as f32;
+        let media = valores.clone().sum::<f32>() / n;
+        let variancia = valores.clone().map(|v| (v - media).powi(2)).sum::<f32>() / n;
+        Estatisticas {
+            min: valores.clone().fold(f32::INFINITY, f32::min),
+            max: valores.fold(f32::NEG_INFINITY, f32::max),
+            media,
+            desvio: variancia.sqrt(),
+        }
+    }
+}
+
+/*
+Janela de um pé alimentada por amostras gravadas. O instante de cada amostra é a origem mais o
+tempo decorrido desde a primeira, tirado de instante_ms; amostras sem ele, gravadas antes da
+coluna existir, contam como um periodo depois da anterior. A janela recomeça quando a sessão
+muda ou o instante volta no tempo.
+*/
+#[derive(Clone, Debug)]
+pub struct FluxoGravado {
+    janela: JanelaCaracteristicas,
+    origem: Instant,
+    periodo: Duration,
+    //sessão, instante atribuído e instante_ms da última amostra
+    ultima: Option<(Option<u32>, Instant, Option<u64>)>,
+}
+
+impl FluxoGravado {
+    pub fn new(espec: EspecCaracteristicas, origem: Instant, periodo: Duration) -> FluxoGravado {
+        FluxoGravado {
+            janela: JanelaCaracteristicas::new(espec),
+            origem,
+            periodo,
+            ultima: None,
+        }
+    }
+
+    /*
+    Adiciona o dado e retorna o vetor da especificação
+    */
+    pub fn atualizar(&mut self, dado: &DadoPapete) -> Vec<f32> {
+        let agora = match self.ultima {
+            Some((sessao, agora, instante_ms))
+                if sessao == dado.sessao && instante_ms <= dado.instante_ms =>
+            {
+                agora
+                    + match (instante_ms, dado.instante_ms) {
+                        (Some(anterior), Some(atual)) => Duration::from_millis(atual - anterior),
+                        _ => self.periodo,
+                    }
+            }
+            _ => {
+                self.janela.limpar();
+                self.origem
+            }
+        };
+        self.ultima = Some((dado.sessao, agora, dado.instante_ms));
+        self.janela.atualizar(dado, agora)
+    }
+}
+
+/*
+Vetores de um dataset gravado, tratando as linhas de cada pé como um FluxoGravado
+*/
+pub fn extrair_dataset(
+    espec: &EspecCaracteristicas,
+    dados: &[DadoPapete],
+    periodo: Duration,
+) -> Vec<Vec<f32>> {
+    let origem = Instant::now();
+    let mut fluxos = PorLado::new(
+        FluxoGravado::new(espec.clone(), origem, periodo),
+        FluxoGravado::new(espec.clone(), origem, periodo),
+    );
+    dados
+        .iter()
+        .map(|dado| fluxos[dado.lado()].atualizar(dado))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::caracteristicas::*;
+
+    #[test]
+    fn janela_de_um_pe() {
+        let origem = Instant::now();
+        let mut janela = JanelaCaracteristicas::new(EspecCaracteristicas {
+            limiar_repouso: 0.05,
+            ..EspecCaracteristicas::dinamica(200)
+        });
+        let mut ultimo = Vec::new();
+        //repouso e depois dorsiflexão de 0.1 rad a cada 100 ms
+        for i in 0..6 {
+            let pitch = -0.1 * i as f32;
+            let dado = DadoPapete::basico(pitch, 0.0, true);
+            ultimo = janela.atualizar(&dado, origem + Duration::from_millis(100 * i));
+        }
+        let valor = |c: Caracteristica| {
+            ultimo[Caracteristica::todas()
+                .iter()
+                .position(|&x| x == c)
+                .unwrap()]
+        };
+        assert_eq!(ultimo.len(), 16);
+        assert!((valor(Caracteristica::DeltaPitch) + 0.1).abs() < 1e-6);
+        //janela de 200 ms: amostras de 300, 400 e 500 ms
+        assert!((valor(Caracteristica::VelocidadePitch) + 1.0).abs() < 1e-5);
+        assert!((valor(Caracteristica::MinPitch) + 0.5).abs() < 1e-6);
+        assert!((valor(Caracteristica::MaxPitch) + 0.3).abs() < 1e-6);
+        assert!((valor(Caracteristica::MediaPitch) + 0.4).abs() < 1e-6);
+        //só a primeira amostra estava em repouso
+        assert!((valor(Caracteristica::TempoDesdeRepouso) - 0.5).abs() < 1e-6);
+
+        //a especificação instantânea é a entrada dos modelos atuais
+        let dado = DadoPapete::basico(0.3, -0.2, false);
+        let dados = [dado, dado];
+        let instantanea = extrair_dataset(
+            &EspecCaracteristicas::instantanea(),
+            &dados,
+            Duration::from_millis(50),
+        );
+        assert_eq!(instantanea[1], Vec::from(dado.array_normalizado()));
+
+        //no dataset gravado o tempo vem do instante de cada amostra, não do periodo
+        let espec = EspecCaracteristicas {
+            caracteristicas: vec![Caracteristica::VelocidadePitch],
+            ..EspecCaracteristicas::dinamica(1_000)
+        };
+        let mut gravados = [
+            DadoPapete::basico(0.0, 0.0, true),
+            DadoPapete::basico(-0.4, 0.0, true),
+        ];
+        gravados[0].instante_ms = Some(10_000);
+        gravados[1].instante_ms = Some(10_200);
+        let vetores = extrair_dataset(&espec, &gravados, Duration::from_millis(50));
+        assert!((vetores[1][0] + 2.0).abs() < 1e-5);
+    }
+}
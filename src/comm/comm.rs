@@ -159,7 +159,7 @@ impl Comm {
         rx: Receiver<()>,
         filtro: Option<&'static (dyn Fn(&str) -> bool + Sync)>,
     ) {
-        match serialport::new(Cow::from(&porta), 9600)
+        match serialport::new(Cow::from(&porta), 115200)
             .timeout(Duration::from_millis(60))
             .open()
         {
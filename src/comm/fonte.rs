@@ -0,0 +1,120 @@
+/*
+De onde a Papete tira as leituras. Sensores abre portas seriais e UDP em threads próprias;
+FonteMemoria só devolve o que foi colocado nela, para testes e para embutir a biblioteca
+onde não há (ou não se quer) comunicação.
+*/
+
+use crate::comm::orientacao::{ConfigOrientacao, Orientacao};
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+pub trait FonteSensores: Send {
+    /*
+    Nomes dos dispositivos que mandaram dados recentemente
+    */
+    fn obter_sensores_ativos(&self) -> Vec<String>;
+
+    /*
+    Nome e valores de cada dispositivo ativo
+    */
+    fn obter_leituras(&self) -> Vec<(String, Vec<f32>)>;
+
+    /*
+    Orientação fundida de cada dispositivo que manda o giroscópio; vazia nas fontes sem filtro
+    */
+    fn obter_orientacoes(&self) -> Vec<(String, Orientacao)> {
+        Vec::new()
+    }
+
+    fn configurar_orientacao(&self, _config: ConfigOrientacao) {}
+}
+
+pub type Leitura = (String, Vec<f32>);
+
+#[derive(Default)]
+struct EstadoMemoria {
+    atuais: Vec<Leitura>,
+    //cada obter_leituras consome um quadro; sem quadros, repete as leituras atuais
+    roteiro: VecDeque<Vec<Leitura>>,
+    //quadro devolvido pela última obter_leituras; vale só até a próxima
+    quadro: Option<Vec<Leitura>>,
+}
+
+/*
+Fonte roteirizada em memória. Clones compartilham o mesmo estado, então quem a passou
+para a Papete continua podendo mudar as leituras.
+Os quadros do roteiro são passageiros: não mudam as leituras atuais, que voltam a valer
+assim que o roteiro acaba.
+*/
+#[derive(Clone, Default)]
+pub struct FonteMemoria {
+    estado: Arc<Mutex<EstadoMemoria>>,
+}
+
+#[allow(dead_code)]
+impl FonteMemoria {
+    pub fn new() -> FonteMemoria {
+        FonteMemoria::default()
+    }
+
+    pub fn com_roteiro(quadros: Vec<Vec<Leitura>>) -> FonteMemoria {
+        let fonte = FonteMemoria::new();
+        for quadro in quadros {
+            fonte.adicionar_quadro(quadro);
+        }
+        fonte
+    }
+
+    /*
+    Muda as leituras atuais de um dispositivo, conectando-o se preciso
+    */
+    pub fn definir(&self, dispositivo: &str, valores: &[f32]) {
+        let mut estado = self.estado.lock().unwrap();
+        match estado.atuais.iter_mut().find(|l| l.0 == dispositivo) {
+            Some(leitura) => leitura.1 = valores.to_vec(),
+            None => estado
+                .atuais
+                .push((dispositivo.to_string(), valores.to_vec())),
+        }
+    }
+
+    /*
+    Desconecta o dispositivo
+    */
+    pub fn remover(&self, dispositivo: &str) {
+        self.estado
+            .lock()
+            .unwrap()
+            .atuais
+            .retain(|l| l.0 != dispositivo);
+    }
+
+    pub fn adicionar_quadro(&self, quadro: Vec<Leitura>) {
+        self.estado.lock().unwrap().roteiro.push_back(quadro);
+    }
+}
+
+impl FonteSensores for FonteMemoria {
+    fn obter_sensores_ativos(&self) -> Vec<String> {
+        let estado = self.estado.lock().unwrap();
+        estado
+            .quadro
+            .as_ref()
+            .unwrap_or(&estado.atuais)
+            .iter()
+            .map(|l| l.0.clone())
+            .collect()
+    }
+
+    fn obter_leituras(&self) -> Vec<Leitura> {
+        let mut estado = self.estado.lock().unwrap();
+        estado.quadro = estado.roteiro.pop_front();
+        match &estado.quadro {
+            Some(quadro) => quadro.clone(),
+            None => estado.atuais.clone(),
+        }
+    }
+}
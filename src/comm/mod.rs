@@ -1,2 +1,4 @@
 pub mod comm;
+pub mod fonte;
+pub mod orientacao;
 pub mod sensor;
\ No newline at end of file
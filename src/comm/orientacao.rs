@@ -0,0 +1,261 @@
+/*
+Fusão do acelerômetro com o giroscópio para estimar a orientação de cada dispositivo.
+Os ângulos só do acelerômetro são ruidosos e, em movimentos rápidos, a aceleração do pé se
+soma à gravidade; o giroscópio não tem esse problema mas deriva, então cada filtro integra
+o giroscópio e corrige com o acelerômetro aos poucos.
+
+Os ângulos seguem as mesmas contas do firmware (pitch = atan2(-ax, sqrt(ay² + az²)),
+roll = atan2(ay, az)), para que os modelos treinados continuem valendo. Sem magnetômetro,
+o yaw é só o giroscópio integrado e deriva com o tempo.
+*/
+
+use std::f32::consts::PI;
+
+const GRAVIDADE: f32 = 9.80665;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TipoFiltro {
+    Complementar,
+    Madgwick,
+}
+
+/*
+- alfa: peso do giroscópio no filtro complementar, de 0 a 1;
+- beta: ganho da correção pelo acelerômetro no Madgwick (rad/s);
+- tolerancia_acel: quanto o módulo da aceleração pode se afastar da gravidade, em fração dela,
+  para o acelerômetro ainda corrigir. Fora disso, só o giroscópio é usado.
+*/
+#[derive(Clone, Copy, Debug)]
+pub struct ConfigOrientacao {
+    pub filtro: TipoFiltro,
+    pub alfa: f32,
+    pub beta: f32,
+    pub tolerancia_acel: f32,
+}
+
+impl Default for ConfigOrientacao {
+    fn default() -> Self {
+        ConfigOrientacao {
+            filtro: TipoFiltro::Complementar,
+            alfa: 0.98,
+            beta: 0.1,
+            tolerancia_acel: 0.15,
+        }
+    }
+}
+
+/*
+Ângulos em rad
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Orientacao {
+    pub pitch: f32,
+    pub roll: f32,
+    pub yaw: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct FiltroOrientacao {
+    config: ConfigOrientacao,
+    orientacao: Orientacao,
+    //quatérnio do Madgwick: w, x, y, z
+    q: [f32; 4],
+    iniciado: bool,
+}
+
+impl FiltroOrientacao {
+    pub fn new(config: ConfigOrientacao) -> FiltroOrientacao {
+        FiltroOrientacao {
+            config,
+            orientacao: Orientacao::default(),
+            q: [1.0, 0.0, 0.0, 0.0],
+            iniciado: false,
+        }
+    }
+
+    pub fn orientacao(&self) -> Orientacao {
+        self.orientacao
+    }
+
+    /*
+    acel em m/s², giro em rad/s e dt em s. A primeira leitura, ou uma depois de um intervalo
+    inválido, começa dos ângulos do acelerômetro.
+    */
+    pub fn atualizar(&mut self, acel: [f32; 3], giro: [f32; 3], dt: f32) -> Orientacao {
+        if !(self.iniciado && dt > 0.0 && dt < 0.5) {
+            let (pitch, roll) = angulos_acel(acel);
+            self.orientacao = Orientacao {
+                pitch,
+                roll,
+                yaw: self.orientacao.yaw,
+            };
+            self.q = quaternio(&self.orientacao);
+            self.iniciado = true;
+            return self.orientacao;
+        }
+        let modulo = (acel[0] * acel[0] + acel[1] * acel[1] + acel[2] * acel[2]).sqrt();
+        let confiavel = (modulo / GRAVIDADE - 1.0).abs() <= self.config.tolerancia_acel;
+        self.orientacao = match self.config.filtro {
+            TipoFiltro::Complementar => self.complementar(acel, giro, dt, confiavel),
+            TipoFiltro::Madgwick => self.madgwick(acel, giro, dt, confiavel),
+        };
+        self.orientacao
+    }
+
+    fn complementar(
+        &mut self,
+        acel: [f32; 3],
+        giro: [f32; 3],
+        dt: f32,
+        confiavel: bool,
+    ) -> Orientacao {
+        let Orientacao { pitch, roll, yaw } = self.orientacao;
+        let [p, q, r] = giro;
+        //taxas dos ângulos de Euler a partir das do corpo
+        let (sen_roll, cos_roll) = roll.sin_cos();
+        let cos_pitch = pitch.cos().max(1e-3);
+        let vertical = q * sen_roll + r * cos_roll;
+        let mut nova = Orientacao {
+            pitch: pitch + (q * cos_roll - r * sen_roll) * dt,
+            roll: normalizar(roll + (p + vertical * pitch.tan()) * dt),
+            yaw: normalizar(yaw + vertical / cos_pitch * dt),
+        };
+        if confiavel {
+            let (pitch_acel, roll_acel) = angulos_acel(acel);
+            let peso = 1.0 - self.config.alfa;
+            nova.pitch += peso * (pitch_acel - nova.pitch);
+            nova.roll = normalizar(nova.roll + peso * normalizar(roll_acel - nova.roll));
+        }
+        self.q = quaternio(&nova);
+        nova
+    }
+
+    fn madgwick(&mut self, acel: [f32; 3], giro: [f32; 3], dt: f32, confiavel: bool) -> Orientacao {
+        let [q0, q1, q2, q3] = self.q;
+        let [gx, gy, gz] = giro;
+        let mut derivada = [
+            0.5 * (-q1 * gx - q2 * gy - q3 * gz),
+            0.5 * (q0 * gx + q2 * gz - q3 * gy),
+            0.5 * (q0 * gy - q1 * gz + q3 * gx),
+            0.5 * (q0 * gz + q1 * gy - q2 * gx),
+        ];
+        if confiavel {
+            //passo do gradiente entre a gravidade esperada pelo quatérnio e a medida
+            let a = normalizado(acel);
+            let f = [
+                2.0 * (q1 * q3 - q0 * q2) - a[0],
+                2.0 * (q0 * q1 + q2 * q3) - a[1],
+                2.0 * (0.5 - q1 * q1 - q2 * q2) - a[2],
+            ];
+            let passo = normalizado([
+                -2.0 * q2 * f[0] + 2.0 * q1 * f[1],
+                2.0 * q3 * f[0] + 2.0 * q0 * f[1] - 4.0 * q1 * f[2],
+                -2.0 * q0 * f[0] + 2.0 * q3 * f[1] - 4.0 * q2 * f[2],
+                2.0 * q1 * f[0] + 2.0 * q2 * f[1],
+            ]);
+            for (d, s) in derivada.iter_mut().zip(passo) {
+                *d -= self.config.beta * s;
+            }
+        }
+        let mut q = self.q;
+        for (q, d) in q.iter_mut().zip(derivada) {
+            *q += d * dt;
+        }
+        self.q = normalizado(q);
+        euler(&self.q)
+    }
+}
+
+/*
+Mesmas contas do firmware
+*/
+pub fn angulos_acel(acel: [f32; 3]) -> (f32, f32) {
+    let [x, y, z] = acel;
+    ((-x).atan2((y * y + z * z).sqrt()), y.atan2(z))
+}
+
+fn normalizar(angulo: f32) -> f32 {
+    (angulo + PI).rem_euclid(2.0 * PI) - PI
+}
+
+fn normalizado<const N: usize>(v: [f32; N]) -> [f32; N] {
+    let modulo = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if modulo > 0.0 {
+        v.map(|x| x / modulo)
+    } else {
+        v
+    }
+}
+
+fn quaternio(o: &Orientacao) -> [f32; 4] {
+    let (sr, cr) = (o.roll * 0.5).sin_cos();
+    let (sp, cp) = (o.pitch * 0.5).sin_cos();
+    let (sy, cy) = (o.yaw * 0.5).sin_cos();
+    [
+        cr * cp * cy + sr * sp * sy,
+        sr * cp * cy - cr * sp * sy,
+        cr * sp * cy + sr * cp * sy,
+        cr * cp * sy - sr * sp * cy,
+    ]
+}
+
+fn euler(q: &[f32; 4]) -> Orientacao {
+    let [q0, q1, q2, q3] = *q;
+    Orientacao {
+        pitch: (2.0 * (q0 * q2 - q3 * q1)).clamp(-1.0, 1.0).asin(),
+        roll: (2.0 * (q0 * q1 + q2 * q3)).atan2(1.0 - 2.0 * (q1 * q1 + q2 * q2)),
+        yaw: (2.0 * (q0 * q3 + q1 * q2)).atan2(1.0 - 2.0 * (q2 * q2 + q3 * q3)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comm::orientacao::*;
+
+    //aceleração medida parada com esses ângulos
+    fn gravidade(pitch: f32, roll: f32) -> [f32; 3] {
+        [
+            -GRAVIDADE * pitch.sin(),
+            GRAVIDADE * roll.sin() * pitch.cos(),
+            GRAVIDADE * roll.cos() * pitch.cos(),
+        ]
+    }
+
+    #[test]
+    fn fusao() {
+        for filtro in [TipoFiltro::Complementar, TipoFiltro::Madgwick] {
+            let config = ConfigOrientacao {
+                filtro,
+                ..Default::default()
+            };
+
+            //parado depois de inclinar: converge para os ângulos do acelerômetro
+            let mut f = FiltroOrientacao::new(config);
+            f.atualizar(gravidade(0.0, 0.0), [0.0; 3], 0.05);
+            for _ in 0..300 {
+                f.atualizar(gravidade(-0.3, 0.2), [0.0; 3], 0.05);
+            }
+            let o = f.orientacao();
+            assert!((o.pitch + 0.3).abs() < 1e-2, "{:?} {:?}", filtro, o);
+            assert!((o.roll - 0.2).abs() < 1e-2, "{:?} {:?}", filtro, o);
+
+            //girando o roll a 1 rad/s com a aceleração corrompida: só o giroscópio vale
+            let mut f = FiltroOrientacao::new(config);
+            f.atualizar(gravidade(0.0, 0.0), [0.0; 3], 0.05);
+            for _ in 0..10 {
+                f.atualizar([5.0, 0.0, 20.0], [1.0, 0.0, 0.0], 0.05);
+            }
+            let o = f.orientacao();
+            assert!((o.roll - 0.5).abs() < 1e-3, "{:?} {:?}", filtro, o);
+            assert!(o.pitch.abs() < 1e-3 && o.yaw.abs() < 1e-3);
+
+            //o yaw integra a rotação em torno do eixo vertical
+            let mut f = FiltroOrientacao::new(config);
+            for _ in 0..11 {
+                f.atualizar(gravidade(0.0, 0.0), [0.0, 0.0, 0.5], 0.1);
+            }
+            assert!((f.orientacao().yaw - 0.5).abs() < 1e-3, "{:?}", filtro);
+        }
+    }
+}
@@ -1,4 +1,8 @@
-use crate::comm::comm::Comm;
+use crate::comm::{
+    comm::Comm,
+    fonte::FonteSensores,
+    orientacao::{ConfigOrientacao, FiltroOrientacao, Orientacao},
+};
 use queue::Queue;
 use std::{
     sync::{Arc, Mutex},
@@ -11,12 +15,19 @@ static LISTA_BRANCA: [&'static str; 4] = ["papE", "papD", "luvaE", "luvaD"];
 #[derive(Debug)]
 pub struct Sensor {
     pub device: String,
+    /*
+    Na ordem da mensagem. Nas papetes: pitch, roll e, se o firmware mandar,
+    acelerômetro x, y, z, giroscópio x, y, z e temperatura (ver DadoPapete::de_valores).
+    Com o giroscópio, pitch e roll já são os do filtro de orientação.
+    */
     pub values: Vec<f32>,
     pub time: time::SystemTime,
+    pub filtro: Option<FiltroOrientacao>,
 }
 
 pub struct Sensores {
     pub sensores: Arc<Mutex<Vec<Sensor>>>,
+    config_orientacao: Arc<Mutex<ConfigOrientacao>>,
     _comm: Comm,
 }
 
@@ -44,10 +55,13 @@ impl Sensores {
 
         let sensores = Arc::new(Mutex::new(Vec::new()));
         let copia = Arc::clone(&sensores);
+        let config_orientacao = Arc::new(Mutex::new(ConfigOrientacao::default()));
+        let copia_config = Arc::clone(&config_orientacao);
 
-        thread::spawn(move || Sensores::listener(queue, copia));
+        thread::spawn(move || Sensores::listener(queue, copia, copia_config));
         let s = Sensores {
             sensores,
+            config_orientacao,
             _comm: comm,
         };
         s
@@ -61,7 +75,11 @@ impl Sensores {
             .map(|s| s.device.to_string())
             .collect()
     }
-    fn listener(queue: Arc<Mutex<Queue<String>>>, sensores: Arc<Mutex<Vec<Sensor>>>) {
+    fn listener(
+        queue: Arc<Mutex<Queue<String>>>,
+        sensores: Arc<Mutex<Vec<Sensor>>>,
+        config_orientacao: Arc<Mutex<ConfigOrientacao>>,
+    ) {
         let re = regex::Regex::new(r"^-?(0|[1-9]\d*)(\.\d+)?").unwrap();
         let mut filtrou;
         loop {
@@ -83,18 +101,35 @@ impl Sensores {
                             }
                         }
                         if let Ok(mut s) = sensores.lock() {
-                            if let Some(index) =
-                                s.iter().position(|sensor| &sensor.device == device)
-                            {
-                                s[index].values = values;
-                                s[index].time = time::SystemTime::now();
-                            } else {
-                                s.push(Sensor {
-                                    device: device.to_string(),
-                                    values,
-                                    time: time::SystemTime::now(),
-                                })
+                            let agora = time::SystemTime::now();
+                            let index = match s.iter().position(|sensor| &sensor.device == device) {
+                                Some(index) => index,
+                                None => {
+                                    s.push(Sensor {
+                                        device: device.to_string(),
+                                        values: Vec::new(),
+                                        time: agora,
+                                        filtro: None,
+                                    });
+                                    s.len() - 1
+                                }
+                            };
+                            let sensor = &mut s[index];
+                            //com acelerômetro e giroscópio, troca pitch e roll pelos do filtro
+                            if let [_, _, ax, ay, az, gx, gy, gz, ..] = values[..] {
+                                let dt = agora
+                                    .duration_since(sensor.time)
+                                    .map(|d| d.as_secs_f32())
+                                    .unwrap_or(0.0);
+                                let filtro = sensor.filtro.get_or_insert_with(|| {
+                                    FiltroOrientacao::new(*config_orientacao.lock().unwrap())
+                                });
+                                let o = filtro.atualizar([ax, ay, az], [gx, gy, gz], dt);
+                                values[0] = o.pitch;
+                                values[1] = o.roll;
                             }
+                            sensor.values = values;
+                            sensor.time = agora;
 
                             //retira da lista itens que estão a mais tempo que o necessario
                             s.retain(|sensor| match &sensor.time.elapsed() {
@@ -119,6 +154,47 @@ impl Sensores {
         }
     }
 
+    /*
+    Nome e valores de cada sensor ativo, lidos juntos para que não se desencontrem
+    */
+    pub fn obter_leituras(&self) -> Vec<(String, Vec<f32>)> {
+        match self.sensores.lock() {
+            Ok(sensores) => sensores
+                .iter()
+                .filter(|s| s.time.elapsed().map(|t| t.as_secs() < TIMEOUT).unwrap_or(true))
+                .map(|s| (s.device.clone(), s.values.clone()))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /*
+    Orientação estimada de cada sensor ativo que manda o giroscópio
+    */
+    pub fn obter_orientacoes(&self) -> Vec<(String, Orientacao)> {
+        match self.sensores.lock() {
+            Ok(sensores) => sensores
+                .iter()
+                .filter(|s| s.time.elapsed().map(|t| t.as_secs() < TIMEOUT).unwrap_or(true))
+                .filter_map(|s| s.filtro.as_ref().map(|f| (s.device.clone(), f.orientacao())))
+                .collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /*
+    Troca o filtro e os ganhos; os filtros recomeçam na próxima leitura de cada sensor
+    */
+    pub fn configurar_orientacao(&self, config: ConfigOrientacao) {
+        *self.config_orientacao.lock().unwrap() = config;
+        if let Ok(mut sensores) = self.sensores.lock() {
+            for sensor in sensores.iter_mut() {
+                sensor.filtro = None;
+            }
+        }
+    }
+
+    #[allow(dead_code)]
     pub fn obter_valores(&self, buffer: &mut Vec<Vec<f32>>) {
         if let Ok(sensores) = self.sensores.lock() {
             //para cada um dos sensores
@@ -141,3 +217,21 @@ impl Sensores {
         }
     }
 }
+
+impl FonteSensores for Sensores {
+    fn obter_sensores_ativos(&self) -> Vec<String> {
+        Sensores::obter_sensores_ativos(self)
+    }
+
+    fn obter_leituras(&self) -> Vec<(String, Vec<f32>)> {
+        Sensores::obter_leituras(self)
+    }
+
+    fn obter_orientacoes(&self) -> Vec<(String, Orientacao)> {
+        Sensores::obter_orientacoes(self)
+    }
+
+    fn configurar_orientacao(&self, config: ConfigOrientacao) {
+        Sensores::configurar_orientacao(self, config)
+    }
+}
@@ -0,0 +1,281 @@
+/*
+Compara todos os previsores registrados sobre as mesmas pastas de validação cruzada,
+para que as diferenças de acurácia não venham de embaralhamentos diferentes.
+*/
+
+use crate::{
+    arvore::Arvore,
+    avaliacao::{self, ConfigValidacao, MatrizConfusao, RelatorioAvaliacao},
+    balanceamento::ConfigTreino,
+    dado_papete::DadoPapete,
+    neural::Neural,
+    previsor::Previsor,
+};
+
+use serde::Serialize;
+use std::time::Instant;
+
+pub type Construtor =
+    fn(&[DadoPapete], &ConfigTreino) -> Result<Box<dyn Previsor>, Box<dyn std::error::Error>>;
+
+pub fn construtor<T: Previsor + 'static>() -> Construtor {
+    |dados, config| Ok(Box::new(T::calcular_de_dataset_com(dados, None, config)?))
+}
+
+/*
+Novos modelos entram na comparação sendo adicionados aqui
+*/
+pub fn previsores_registrados() -> Vec<(&'static str, Construtor)> {
+    vec![
+        ("arvore", construtor::<Arvore>()),
+        ("neural", construtor::<Neural>()),
+    ]
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ResultadoModelo {
+    pub nome: String,
+    pub relatorio: RelatorioAvaliacao,
+    //médias por pasta
+    pub tempo_treino_s: f64,
+    pub latencia_us: f64,
+    //acerto de cada amostra, na ordem das pastas, para os testes pareados
+    #[serde(skip)]
+    acertos: Vec<bool>,
+}
+
+/*
+Testes pareados entre dois modelos:
+- McNemar sobre as amostras em que só um deles acerta (com correção de continuidade);
+- t pareado sobre as taxas de acerto de cada pasta.
+*/
+#[derive(Clone, Debug, Serialize)]
+pub struct ComparacaoPar {
+    pub modelo_a: String,
+    pub modelo_b: String,
+    //amostras que só A acertou e que só B acertou
+    pub so_a: usize,
+    pub so_b: usize,
+    pub qui_quadrado: f64,
+    pub p_mcnemar: f64,
+    pub t: f64,
+    pub graus_liberdade: usize,
+    //|t| acima do valor crítico bicaudal de 5%
+    pub t_significativo: bool,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Comparacao {
+    pub modelos: Vec<ResultadoModelo>,
+    pub pares: Vec<ComparacaoPar>,
+}
+
+pub fn comparar(
+    dados: &[DadoPapete],
+    previsores: &[(&str, Construtor)],
+    config_validacao: &ConfigValidacao,
+    config_treino: &ConfigTreino,
+) -> Result<Comparacao, Box<dyn std::error::Error>> {
+    let pastas = avaliacao::separar_pastas(dados, config_validacao);
+
+    let mut modelos = Vec::with_capacity(previsores.len());
+    for (nome, construtor) in previsores {
+        let mut esperado = Vec::with_capacity(dados.len());
+        let mut obtido = Vec::with_capacity(dados.len());
+        let mut acertos = Vec::with_capacity(dados.len());
+        let mut taxas = Vec::with_capacity(pastas.len());
+        let mut tempo_treino = 0.0;
+        let mut tempo_previsao = 0.0;
+
+        for (k, (_, indices_teste)) in pastas.iter().enumerate() {
+            println!("{} {}/{}", nome, k + 1, pastas.len());
+            let mut no_teste = vec![false; dados.len()];
+            for i in indices_teste {
+                no_teste[*i] = true;
+            }
+            let dados_treino: Vec<DadoPapete> = dados
+                .iter()
+                .zip(no_teste.iter())
+                .filter(|(_, teste)| !**teste)
+                .map(|(x, _)| *x)
+                .collect();
+
+            let inicio = Instant::now();
+            let mut previsor = construtor(&dados_treino, config_treino)?;
+            tempo_treino += inicio.elapsed().as_secs_f64();
+
+            let mut acertos_pasta = 0;
+            for i in indices_teste {
+                let inicio = Instant::now();
+                let resposta = previsor.prever(dados[*i]);
+                tempo_previsao += inicio.elapsed().as_secs_f64();
+
+                let acertou = Some(resposta) == dados[*i].movimento;
+                acertos_pasta += acertou as usize;
+                acertos.push(acertou);
+                esperado.push(dados[*i].movimento.unwrap());
+                obtido.push(resposta);
+            }
+            taxas.push(acertos_pasta as f32 / indices_teste.len().max(1) as f32);
+        }
+
+        modelos.push(ResultadoModelo {
+            nome: nome.to_string(),
+            relatorio: RelatorioAvaliacao::new(
+                MatrizConfusao::new(esperado.into_iter(), obtido.into_iter()),
+                taxas,
+            ),
+            tempo_treino_s: tempo_treino / pastas.len().max(1) as f64,
+            latencia_us: tempo_previsao * 1e6 / acertos.len().max(1) as f64,
+            acertos,
+        });
+    }
+
+    let mut pares = Vec::new();
+    for i in 0..modelos.len() {
+        for j in i + 1..modelos.len() {
+            pares.push(comparar_par(&modelos[i], &modelos[j]));
+        }
+    }
+    Ok(Comparacao { modelos, pares })
+}
+
+fn comparar_par(a: &ResultadoModelo, b: &ResultadoModelo) -> ComparacaoPar {
+    let so_a = a
+        .acertos
+        .iter()
+        .zip(b.acertos.iter())
+        .filter(|(x, y)| **x && !**y)
+        .count();
+    let so_b = a
+        .acertos
+        .iter()
+        .zip(b.acertos.iter())
+        .filter(|(x, y)| !**x && **y)
+        .count();
+    let discordantes = (so_a + so_b) as f64;
+    let qui_quadrado = if discordantes > 0.0 {
+        ((so_a as f64 - so_b as f64).abs() - 1.0).max(0.0).powi(2) / discordantes
+    } else {
+        0.0
+    };
+
+    let diferencas: Vec<f64> = a
+        .relatorio
+        .taxas_pastas
+        .iter()
+        .zip(b.relatorio.taxas_pastas.iter())
+        .map(|(x, y)| (*x - *y) as f64)
+        .collect();
+    let graus_liberdade = diferencas.len().saturating_sub(1);
+    let t = if graus_liberdade > 0 {
+        let media = diferencas.iter().sum::<f64>() / diferencas.len() as f64;
+        let variancia =
+            diferencas.iter().map(|d| (d - media).powi(2)).sum::<f64>() / graus_liberdade as f64;
+        if variancia > 0.0 {
+            media / (variancia / diferencas.len() as f64).sqrt()
+        } else {
+            0.0
+        }
+    } else {
+        0.0
+    };
+
+    ComparacaoPar {
+        modelo_a: a.nome.clone(),
+        modelo_b: b.nome.clone(),
+        so_a,
+        so_b,
+        qui_quadrado,
+        //qui-quadrado com 1 grau de liberdade
+        p_mcnemar: erfc((qui_quadrado / 2.0).sqrt()),
+        t,
+        graus_liberdade,
+        t_significativo: graus_liberdade > 0
+            && t.abs() > avaliacao::t_critico_95(graus_liberdade) as f64,
+    }
+}
+
+//Abramowitz e Stegun 7.1.26, erro absoluto < 1.5e-7
+fn erfc(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polinomio = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let resultado = polinomio * (-x * x).exp();
+    if x >= 0.0 {
+        resultado
+    } else {
+        2.0 - resultado
+    }
+}
+
+impl std::fmt::Display for Comparacao {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:<10}{:>10}{:>20}{:>10}{:>10}{:>12}{:>14}",
+            "modelo", "acurácia", "IC95%", "F1 macro", "kappa", "treino (s)", "latência (µs)"
+        )?;
+        for m in &self.modelos {
+            let r = &m.relatorio;
+            writeln!(
+                f,
+                "{:<10}{:>10.4}{:>20}{:>10.4}{:>10.4}{:>12.3}{:>14.1}",
+                m.nome,
+                r.acuracia,
+                format!("{:.4} a {:.4}", r.ic_acuracia.0, r.ic_acuracia.1),
+                r.macro_f1,
+                r.kappa,
+                m.tempo_treino_s,
+                m.latencia_us
+            )?;
+        }
+        writeln!(f)?;
+        for p in &self.pares {
+            writeln!(
+                f,
+                "{} x {}: McNemar {}/{} χ²={:.3} p={:.4}; t pareado t({})={:.3}{}",
+                p.modelo_a,
+                p.modelo_b,
+                p.so_a,
+                p.so_b,
+                p.qui_quadrado,
+                p.p_mcnemar,
+                p.graus_liberdade,
+                p.t,
+                if p.t_significativo { " *" } else { "" }
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comparacao::*;
+
+    #[test]
+    fn testes_pareados() {
+        assert!((erfc(0.0) - 1.0).abs() < 1e-7);
+        //qui-quadrado de 3.841 corresponde a p = 0.05
+        assert!((erfc((3.841f64 / 2.0).sqrt()) - 0.05).abs() < 1e-3);
+
+        let modelo = |nome: &str, acertos: Vec<bool>, taxas: Vec<f32>| ResultadoModelo {
+            nome: nome.to_string(),
+            relatorio: RelatorioAvaliacao::new(
+                MatrizConfusao::new(std::iter::empty(), std::iter::empty()),
+                taxas,
+            ),
+            tempo_treino_s: 0.0,
+            latencia_us: 0.0,
+            acertos,
+        };
+        let a = modelo("a", vec![true, true, true, false], vec![0.9, 0.8, 0.85]);
+        let b = modelo("b", vec![false, false, true, false], vec![0.5, 0.45, 0.5]);
+        let par = comparar_par(&a, &b);
+        assert_eq!((par.so_a, par.so_b), (2, 0));
+        assert!((par.qui_quadrado - 0.5).abs() < 1e-9);
+        assert!(par.t > 0.0 && par.t_significativo);
+    }
+}
@@ -0,0 +1,117 @@
+/*
+Controle contínuo: os ângulos corrigidos viram valores de -1 a 1 em cada eixo, escalados pela
+amplitude que o paciente alcançou na calibração, para que 1 seja o máximo dele e não um ângulo fixo.
+
+Sentidos, iguais nos dois pés: pitch positivo é dorsiflexão e negativo flexão plantar;
+roll positivo é eversão e negativo inversão. Ver os sinais dos sensores em arco.rs.
+*/
+
+use crate::{dado_papete::DadoPapete, movimento::Movimento};
+
+/*
+- zona_morta: fração da amplitude perto do repouso que ainda vale 0;
+- expoente: curva de resposta depois da zona morta; 1 é linear, maior deixa o centro mais suave;
+- amplitude_padrao: amplitude (rad) usada nos movimentos sem calibração.
+*/
+#[derive(Clone, Debug)]
+pub struct ConfigControle {
+    pub zona_morta: f32,
+    pub expoente: f32,
+    pub amplitude_padrao: f32,
+}
+
+impl Default for ConfigControle {
+    fn default() -> Self {
+        ConfigControle {
+            zona_morta: 0.1,
+            expoente: 1.0,
+            amplitude_padrao: 0.35,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Controle {
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/*
+amplitudes são as da calibração do pé (rad), indexadas por Movimento::as_usize
+*/
+pub fn calcular(dado: &DadoPapete, amplitudes: &[f32; 5], config: &ConfigControle) -> Controle {
+    let amplitude = |m: Movimento| match amplitudes[m.as_usize()] {
+        a if a > 0.0 => a,
+        _ => config.amplitude_padrao,
+    };
+    let roll = if dado.lado_esq { dado.roll } else { -dado.roll };
+    Controle {
+        pitch: eixo(
+            -dado.pitch,
+            amplitude(Movimento::Dorsiflexao),
+            amplitude(Movimento::Flexao),
+            config,
+        ),
+        roll: eixo(
+            roll,
+            amplitude(Movimento::Eversao),
+            amplitude(Movimento::Inversao),
+            config,
+        ),
+    }
+}
+
+/*
+valor no sentido positivo do eixo, com a amplitude de cada sentido
+*/
+fn eixo(valor: f32, amplitude_pos: f32, amplitude_neg: f32, config: &ConfigControle) -> f32 {
+    let amplitude = if valor >= 0.0 {
+        amplitude_pos
+    } else {
+        amplitude_neg
+    };
+    let fracao = (valor.abs() / amplitude).min(1.0);
+    if fracao <= config.zona_morta {
+        return 0.0;
+    }
+    let fracao = (fracao - config.zona_morta) / (1.0 - config.zona_morta);
+    fracao.powf(config.expoente).copysign(valor)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::controle::*;
+
+    #[test]
+    fn escala_pela_calibracao() {
+        let config = ConfigControle::default();
+        let mut amplitudes = [0.0; 5];
+        amplitudes[Movimento::Dorsiflexao.as_usize()] = 0.2;
+
+        //dorsiflexão (pitch negativo) até a amplitude calibrada satura em 1
+        let c = calcular(&DadoPapete::basico(-0.3, 0.0, true), &amplitudes, &config);
+        assert_eq!((c.pitch, c.roll), (1.0, 0.0));
+        //dentro da zona morta
+        let c = calcular(&DadoPapete::basico(-0.01, 0.01, true), &amplitudes, &config);
+        assert_eq!(c, Controle::default());
+        //flexão sem calibração usa a amplitude padrão
+        let c = calcular(&DadoPapete::basico(0.35, 0.0, true), &amplitudes, &config);
+        assert!((c.pitch + 1.0).abs() < 1e-6);
+
+        //eversão é positiva nos dois pés, com curva quadrática
+        let config = ConfigControle {
+            zona_morta: 0.0,
+            expoente: 2.0,
+            ..config
+        };
+        let esq = calcular(&DadoPapete::basico(0.0, 0.175, true), &amplitudes, &config);
+        let dir = calcular(
+            &DadoPapete::basico(0.0, -0.175, false),
+            &amplitudes,
+            &config,
+        );
+        assert!((esq.roll - 0.25).abs() < 1e-6);
+        assert_eq!(esq, dir);
+    }
+}
@@ -15,13 +15,37 @@ pub fn carregar_dados(endereco: &str) -> std::io::Result<Vec<DadoPapete>> {
     Ok(saida)
 }
 
+const CABECALHO: &str =
+    "pitch;roll;lado;movimento;sessao;acel_x;acel_y;acel_z;giro_x;giro_y;giro_z;temperatura;instante_ms";
+
 pub fn salvar_dados(destino: &str, dados: &[DadoPapete]) -> std::io::Result<()> {
-    let mut arquivo = if let Ok(a) = OpenOptions::new().append(true).open(destino) {
-        a
-    } else {
-        let mut a = File::create(destino)?;
-        a.write_all("pitch;roll;lado;movimento;sessao".as_bytes())?;
-        a
+    //ao acrescentar num arquivo existente, escreve as mesmas colunas do cabeçalho dele;
+    //um arquivo vazio é tratado como novo
+    let colunas_existentes = match File::open(destino) {
+        Ok(a) => {
+            let mut cabecalho = String::new();
+            BufReader::new(a).read_line(&mut cabecalho)?;
+            let cabecalho = cabecalho.trim();
+            if cabecalho.is_empty() {
+                None
+            } else if cabecalho.split(';').count() < 5 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    format!("Cabeçalho de {} não tem as colunas dos dados", destino),
+                ));
+            } else {
+                Some(cabecalho.split(';').count())
+            }
+        }
+        Err(_) => None,
+    };
+    let (mut arquivo, colunas) = match colunas_existentes {
+        Some(colunas) => (OpenOptions::new().append(true).open(destino)?, colunas),
+        None => {
+            let mut a = File::create(destino)?;
+            a.write_all(CABECALHO.as_bytes())?;
+            (a, CABECALHO.split(';').count())
+        }
     };
     for dado in dados {
         write!(
@@ -33,7 +57,114 @@ pub fn salvar_dados(destino: &str, dados: &[DadoPapete]) -> std::io::Result<()>
             dado.movimento.unwrap().str_simplificada(),
             dado.sessao.unwrap()
         )?;
+        //campos do IMU ausentes ficam vazios
+        if colunas > 5 {
+            for campo in dado.campos_imu() {
+                match campo {
+                    Some(v) => write!(&mut arquivo, ";{}", v)?,
+                    None => write!(&mut arquivo, ";")?,
+                }
+            }
+        }
+        if colunas > 12 {
+            match dado.instante_ms {
+                Some(v) => write!(&mut arquivo, ";{}", v)?,
+                None => write!(&mut arquivo, ";")?,
+            }
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::csv_helper::*;
+    use crate::movimento::Movimento;
+
+    fn dados() -> Vec<DadoPapete> {
+        let mut com_imu = DadoPapete::completo(0.1, -0.2, true, Movimento::Repouso, 3);
+        com_imu.acel = Some([0.0, 0.5, 1.0]);
+        com_imu.giro = Some([1.5, 2.0, 2.5]);
+        com_imu.temperatura = Some(30.0);
+        com_imu.instante_ms = Some(1_700_000_000_123);
+        let sem_imu = DadoPapete::completo(-0.3, 0.4, false, Movimento::Repouso, 3);
+        vec![com_imu, sem_imu]
+    }
+
+    //nomes por processo, para execuções simultâneas dos testes não se atrapalharem
+    fn temporario(nome: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("papete_csv_{}_{}.csv", nome, std::process::id()))
+    }
+
+    fn linhas(endereco: &std::path::Path) -> Vec<String> {
+        std::fs::read_to_string(endereco)
+            .unwrap()
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    #[test]
+    fn colunas_seguem_o_cabecalho() {
+        let dados = dados();
+
+        //arquivo novo: sempre todas as colunas
+        let novo = temporario("novo");
+        let _ = std::fs::remove_file(&novo);
+        salvar_dados(novo.to_str().unwrap(), &dados[..1]).unwrap();
+        salvar_dados(novo.to_str().unwrap(), &dados[1..]).unwrap();
+        assert!(linhas(&novo).iter().all(|l| l.split(';').count() == 13));
+        let carregados = carregar_dados(novo.to_str().unwrap()).unwrap();
+        assert_eq!(carregados.len(), 2);
+        assert_eq!(carregados[0].acel, dados[0].acel);
+        assert_eq!(carregados[0].giro, dados[0].giro);
+        assert_eq!(carregados[0].temperatura, dados[0].temperatura);
+        assert_eq!(carregados[0].instante_ms, dados[0].instante_ms);
+        assert_eq!(carregados[1].campos_imu(), [None; 7]);
+        assert_eq!(carregados[1].instante_ms, None);
+        assert!(!carregados[1].lado_esq);
+
+        //arquivo com as colunas do IMU e sem o instante: continua com 12
+        let imu = temporario("imu");
+        std::fs::write(&imu, &CABECALHO[..CABECALHO.rfind(';').unwrap()]).unwrap();
+        salvar_dados(imu.to_str().unwrap(), &dados).unwrap();
+        assert!(linhas(&imu).iter().all(|l| l.split(';').count() == 12));
+        let carregados = carregar_dados(imu.to_str().unwrap()).unwrap();
+        assert_eq!(carregados[0].giro, dados[0].giro);
+        assert_eq!(carregados[0].instante_ms, None);
+
+        //arquivo antigo: continua com 5 colunas
+        let antigo = temporario("antigo");
+        std::fs::write(
+            &antigo,
+            "pitch;roll;lado;movimento;sessao\n0.5;0.5;E;Repouso;1",
+        )
+        .unwrap();
+        salvar_dados(antigo.to_str().unwrap(), &dados).unwrap();
+        assert!(linhas(&antigo).iter().all(|l| l.split(';').count() == 5));
+        let carregados = carregar_dados(antigo.to_str().unwrap()).unwrap();
+        assert_eq!(carregados.len(), 3);
+        assert!(carregados.iter().all(|d| d.campos_imu() == [None; 7]));
+        assert_eq!(carregados[1].pitch, 0.1);
+
+        //arquivo vazio, criado antes de haver amostras: recebe o cabeçalho
+        let vazio = temporario("vazio");
+        std::fs::write(&vazio, "").unwrap();
+        salvar_dados(vazio.to_str().unwrap(), &dados).unwrap();
+        assert_eq!(linhas(&vazio)[0], CABECALHO);
+        assert_eq!(carregar_dados(vazio.to_str().unwrap()).unwrap().len(), 2);
+
+        //cabeçalho sem as colunas dos dados: não acrescenta nada
+        let curto = temporario("curto");
+        std::fs::write(&curto, "pitch;roll").unwrap();
+        assert!(salvar_dados(curto.to_str().unwrap(), &dados).is_err());
+        assert_eq!(linhas(&curto), vec!["pitch;roll"]);
+
+        let _ = std::fs::remove_file(&novo);
+        let _ = std::fs::remove_file(&vazio);
+        let _ = std::fs::remove_file(&curto);
+        let _ = std::fs::remove_file(&imu);
+        let _ = std::fs::remove_file(&antigo);
+    }
+}
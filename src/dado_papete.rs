@@ -1,15 +1,27 @@
+use crate::lado::Lado;
 use crate::movimento::Movimento;
 
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::ops::SubAssign;
 
-#[derive(Clone, Copy, Debug)]
+/*
+acel (m/s²), giro (rad/s) e temperatura (°C) são as leituras brutas do MPU6050, quando o firmware
+as manda. Elas não são corrigidas pelo offset de repouso; só pitch e roll são.
+instante_ms é quando a amostra foi registrada (ms desde a época Unix); dados gravados antes
+dele não o têm.
+*/
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct DadoPapete {
     pub pitch: f32,
     pub roll: f32,
     pub lado_esq: bool,
     pub movimento: Option<Movimento>,
     pub sessao: Option<u32>,
+    pub acel: Option<[f32; 3]>,
+    pub giro: Option<[f32; 3]>,
+    pub temperatura: Option<f32>,
+    pub instante_ms: Option<u64>,
 }
 
 impl DadoPapete {
@@ -20,9 +32,50 @@ impl DadoPapete {
             lado_esq,
             movimento: None,
             sessao: None,
+            acel: None,
+            giro: None,
+            temperatura: None,
+            instante_ms: None,
         }
     }
 
+    /*
+    Valores de uma papete, na ordem em que o firmware manda:
+    pitch, roll[, acel x, y, z[, giro x, y, z[, temperatura]]].
+    None se não houver ao menos pitch e roll.
+    */
+    pub fn de_valores(valores: &[f32], lado_esq: bool) -> Option<DadoPapete> {
+        let mut dado = match valores {
+            [pitch, roll, ..] => DadoPapete::basico(*pitch, *roll, lado_esq),
+            _ => return None,
+        };
+        if let Some(acel) = valores.get(2..5) {
+            dado.acel = Some([acel[0], acel[1], acel[2]]);
+        }
+        if let Some(giro) = valores.get(5..8) {
+            dado.giro = Some([giro[0], giro[1], giro[2]]);
+        }
+        dado.temperatura = valores.get(8).copied();
+        Some(dado)
+    }
+
+    /*
+    Leituras brutas em ordem, com os campos ausentes vazios; usado nas colunas extras do CSV
+    */
+    pub fn campos_imu(&self) -> [Option<f32>; 7] {
+        let acel = self.acel.map(|a| a.map(Some)).unwrap_or([None; 3]);
+        let giro = self.giro.map(|g| g.map(Some)).unwrap_or([None; 3]);
+        [
+            acel[0],
+            acel[1],
+            acel[2],
+            giro[0],
+            giro[1],
+            giro[2],
+            self.temperatura,
+        ]
+    }
+
     pub fn completo(
         pitch: f32,
         roll: f32,
@@ -31,13 +84,14 @@ impl DadoPapete {
         sessao: u32,
     ) -> DadoPapete {
         DadoPapete {
-            pitch,
-            roll,
-            lado_esq,
             movimento: Some(movimento),
             sessao: Some(sessao),
+            ..DadoPapete::basico(pitch, roll, lado_esq)
         }
     }
+    pub fn lado(&self) -> Lado {
+        Lado::de_esq(self.lado_esq)
+    }
     pub fn array_normalizado(&self) -> [f32; 3] {
         [
             self.pitch * 0.5 / std::f32::consts::PI + 0.5,
@@ -64,15 +118,15 @@ impl TryFrom<&str> for DadoPapete {
                             segm = &segm[1..];
                         }
                         let partes: Vec<&str> = segm.split("\t").collect();
-                        if partes.len() == 2 {
-                            let pitch = partes[0].parse::<f32>();
-                            let roll = partes[1].parse::<f32>();
-                            if pitch.is_ok() && roll.is_ok() {
-                                return Ok(DadoPapete::basico(
-                                    pitch.unwrap(),
-                                    roll.unwrap(),
-                                    pe_esq,
-                                ));
+                        if [2, 5, 8, 9].contains(&partes.len()) {
+                            //pitch e roll, talvez seguidos de acelerômetro, giroscópio e temperatura
+                            let valores: Result<Vec<f32>, _> =
+                                partes.iter().map(|p| p.parse::<f32>()).collect();
+                            if let Some(dado) = valores
+                                .ok()
+                                .and_then(|v| DadoPapete::de_valores(&v, pe_esq))
+                            {
+                                return Ok(dado);
                             }
                         } else if partes.len() == 4 {
                             //parse tbm o movimento e sessao
@@ -106,7 +160,7 @@ impl TryFrom<&str> for DadoPapete {
                                 return Err(ParseDadoPapeteError);
                             };
                             if let Ok(movimento) = partes[3].parse::<Movimento>() {
-                                let sessao = if partes.len() == 5 {
+                                let sessao = if partes.len() >= 5 {
                                     if let Ok(sessao) = partes[4].parse::<u32>() {
                                         sessao
                                     } else {
@@ -115,9 +169,32 @@ impl TryFrom<&str> for DadoPapete {
                                 } else {
                                     0
                                 };
-                                return Ok(DadoPapete::completo(
-                                    pitch, roll, pe_esq, movimento, sessao,
-                                ));
+                                let mut dado =
+                                    DadoPapete::completo(pitch, roll, pe_esq, movimento, sessao);
+                                //colunas extras do IMU; vazias quando a leitura não tinha o campo
+                                let mut extras = [None; 7];
+                                for (extra, parte) in extras.iter_mut().zip(partes.iter().skip(5)) {
+                                    if !parte.is_empty() {
+                                        match parte.parse::<f32>() {
+                                            Ok(v) => *extra = Some(v),
+                                            Err(_) => return Err(ParseDadoPapeteError),
+                                        }
+                                    }
+                                }
+                                if let [Some(x), Some(y), Some(z), ..] = extras {
+                                    dado.acel = Some([x, y, z]);
+                                }
+                                if let [_, _, _, Some(x), Some(y), Some(z), _] = extras {
+                                    dado.giro = Some([x, y, z]);
+                                }
+                                dado.temperatura = extras[6];
+                                if let Some(instante) = partes.get(12).filter(|p| !p.is_empty()) {
+                                    match instante.parse::<u64>() {
+                                        Ok(v) => dado.instante_ms = Some(v),
+                                        Err(_) => return Err(ParseDadoPapeteError),
+                                    }
+                                }
+                                return Ok(dado);
                             }
                         }
                     }
@@ -143,3 +220,36 @@ impl SubAssign for DadoPapete {
         self.roll -= other.roll;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::dado_papete::*;
+
+    #[test]
+    fn formatos_de_leitura() {
+        //serial antiga, só os ângulos
+        let d = DadoPapete::try_from("E0.1\t-0.2").unwrap();
+        assert_eq!((d.pitch, d.roll, d.lado_esq), (0.1, -0.2, true));
+        assert_eq!(d.campos_imu(), [None; 7]);
+
+        //serial estendida: acelerômetro, giroscópio e temperatura
+        let d = DadoPapete::try_from("D0.1\t-0.2\t0.5\t0.1\t9.8\t0.01\t0.02\t0.03\t25.5").unwrap();
+        assert!(!d.lado_esq);
+        assert_eq!(d.acel, Some([0.5, 0.1, 9.8]));
+        assert_eq!(d.giro, Some([0.01, 0.02, 0.03]));
+        assert_eq!(d.temperatura, Some(25.5));
+        assert_eq!(
+            DadoPapete::de_valores(&[0.1, -0.2, 0.5, 0.1, 9.8], true)
+                .unwrap()
+                .giro,
+            None
+        );
+
+        //CSV antigo e novo, com colunas vazias
+        let d = DadoPapete::try_from("0.1;-0.2;E;Repouso;3").unwrap();
+        assert_eq!((d.sessao, d.acel), (Some(3), None));
+        let d = DadoPapete::try_from("0.1;-0.2;D;Repouso;3;0.5;0.1;9.8;;;;25.5").unwrap();
+        assert_eq!(d.acel, Some([0.5, 0.1, 9.8]));
+        assert_eq!((d.giro, d.temperatura), (None, Some(25.5)));
+    }
+}
@@ -0,0 +1,261 @@
+/*
+Dicas de dificuldade para os jogos, a partir das tentativas do paciente. Cada fim de movimento é
+uma tentativa, bem-sucedida se alcançou fracao_amplitude da amplitude calibrada e durou pelo menos
+tempo_sustentacao_ms. A cada janela de tentativas:
+- com taxa de sucesso alta e sem fadiga, a amplitude pedida e a sustentação sobem e o intervalo cai;
+- com taxa baixa, a amplitude pedida e a sustentação descem;
+- com fadiga, o intervalo entre estímulos sobe.
+A fadiga é a de AnaliseFadiga, contra a referência do previsor adaptado, para que as dicas e os
+alertas concordem.
+Os parâmetros ficam no perfil, para a progressão continuar entre sessões.
+*/
+
+use crate::{
+    calibracao::distancia,
+    dado_papete::DadoPapete,
+    eventos::{EventoMovimento, TipoEvento},
+    fadiga::AlertasFadiga,
+};
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Clone, Debug)]
+pub struct ConfigDificuldade {
+    //tentativas consideradas na taxa de sucesso, e de quantas em quantas os parâmetros mudam
+    pub janela: usize,
+    pub sucesso_alto: f32,
+    pub sucesso_baixo: f32,
+    //amplitude (rad) usada nos movimentos sem calibração
+    pub amplitude_padrao: f32,
+}
+
+impl Default for ConfigDificuldade {
+    fn default() -> Self {
+        ConfigDificuldade {
+            janela: 10,
+            sucesso_alto: 0.8,
+            sucesso_baixo: 0.5,
+            amplitude_padrao: 0.35,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ParametrosDificuldade {
+    //amplitude pedida, em fração da calibrada
+    pub fracao_amplitude: f32,
+    pub tempo_sustentacao_ms: u32,
+    //tempo sugerido entre um estímulo e o próximo
+    pub intervalo_ms: u32,
+}
+
+impl Default for ParametrosDificuldade {
+    fn default() -> Self {
+        ParametrosDificuldade {
+            fracao_amplitude: 0.6,
+            tempo_sustentacao_ms: 500,
+            intervalo_ms: 3000,
+        }
+    }
+}
+
+/*
+Parâmetros sugeridos e o desempenho que levou a eles. amplitude_media é a fração da calibrada
+alcançada nas últimas tentativas; fadiga é a queda de amplitude de AlertasFadiga, de 0 (nenhuma)
+a 1.
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DicasDificuldade {
+    pub parametros: ParametrosDificuldade,
+    pub taxa_sucesso: f32,
+    pub amplitude_media: f32,
+    pub fadiga: f32,
+    pub tentativas: u32,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Tentativa {
+    sucesso: bool,
+    fracao: f32,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Dificuldade {
+    config: ConfigDificuldade,
+    parametros: ParametrosDificuldade,
+    recentes: VecDeque<Tentativa>,
+    //alertas de fadiga na última tentativa
+    alertas: AlertasFadiga,
+    tentativas: u32,
+    desde_ajuste: usize,
+}
+
+impl Dificuldade {
+    pub fn new(config: ConfigDificuldade, parametros: ParametrosDificuldade) -> Dificuldade {
+        Dificuldade {
+            config,
+            parametros,
+            ..Default::default()
+        }
+    }
+
+    /*
+    Considera o evento se for um fim de movimento. amplitude_calibrada é a do pé e movimento
+    do evento (rad), 0 se não houve calibração; alertas são os de AnaliseFadiga já com esta
+    tentativa. Retorna se a tentativa foi bem-sucedida.
+    */
+    pub fn registrar(
+        &mut self,
+        evento: &EventoMovimento,
+        amplitude_calibrada: f32,
+        alertas: AlertasFadiga,
+    ) -> Option<bool> {
+        if evento.tipo != TipoEvento::Fim {
+            return None;
+        }
+        self.alertas = alertas;
+        let amplitude = distancia(&DadoPapete::basico(
+            evento.pico_pitch,
+            evento.pico_roll,
+            evento.lado_esq,
+        ));
+        let referencia = if amplitude_calibrada > 0.0 {
+            amplitude_calibrada
+        } else {
+            self.config.amplitude_padrao
+        };
+        let fracao = amplitude / referencia;
+        let sucesso = fracao >= self.parametros.fracao_amplitude
+            && evento.duracao_ms >= self.parametros.tempo_sustentacao_ms as u64;
+
+        if self.recentes.len() >= self.config.janela {
+            self.recentes.pop_front();
+        }
+        self.recentes.push_back(Tentativa { sucesso, fracao });
+        self.tentativas += 1;
+        self.desde_ajuste += 1;
+        if self.desde_ajuste >= self.config.janela {
+            self.desde_ajuste = 0;
+            self.ajustar();
+        }
+        Some(sucesso)
+    }
+
+    fn ajustar(&mut self) {
+        let taxa = self.taxa_sucesso();
+        let fadiga = self.alertas.fadiga;
+        let p = &mut self.parametros;
+        if fadiga {
+            p.intervalo_ms = (p.intervalo_ms + 500).min(6000);
+        } else if taxa >= self.config.sucesso_alto {
+            p.fracao_amplitude = (p.fracao_amplitude + 0.05).min(1.0);
+            p.tempo_sustentacao_ms = (p.tempo_sustentacao_ms + 100).min(3000);
+            p.intervalo_ms = p.intervalo_ms.saturating_sub(500).max(1500);
+        }
+        if taxa < self.config.sucesso_baixo {
+            p.fracao_amplitude = (p.fracao_amplitude - 0.05).max(0.3);
+            p.tempo_sustentacao_ms = p.tempo_sustentacao_ms.saturating_sub(100).max(200);
+        }
+    }
+
+    fn taxa_sucesso(&self) -> f32 {
+        let sucessos = self.recentes.iter().filter(|t| t.sucesso).count();
+        sucessos as f32 / self.recentes.len().max(1) as f32
+    }
+
+    fn amplitude_media(&self) -> f32 {
+        self.recentes.iter().map(|t| t.fracao).sum::<f32>() / self.recentes.len().max(1) as f32
+    }
+
+    fn fadiga(&self) -> f32 {
+        self.alertas.queda_amplitude.clamp(0.0, 1.0)
+    }
+
+    /*
+    Recomeça as estatísticas para uma nova sessão, mantendo os parâmetros
+    */
+    pub fn nova_sessao(&mut self) {
+        *self = Dificuldade::new(self.config.clone(), self.parametros);
+    }
+
+    pub fn parametros(&self) -> ParametrosDificuldade {
+        self.parametros
+    }
+
+    pub fn dicas(&self) -> DicasDificuldade {
+        DicasDificuldade {
+            parametros: self.parametros,
+            taxa_sucesso: self.taxa_sucesso(),
+            amplitude_media: self.amplitude_media(),
+            fadiga: self.fadiga(),
+            tentativas: self.tentativas,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::dificuldade::*;
+    use crate::movimento::Movimento;
+
+    fn fim(pitch: f32, duracao_ms: u64) -> EventoMovimento {
+        EventoMovimento {
+            tipo: TipoEvento::Fim,
+            lado_esq: true,
+            movimento: Movimento::Dorsiflexao,
+            instante_ms: 0,
+            duracao_ms,
+            pico_pitch: pitch,
+            pico_roll: 0.0,
+        }
+    }
+
+    #[test]
+    fn progressao() {
+        let mut d = Dificuldade::default();
+        let inicial = d.parametros();
+        let sem_fadiga = AlertasFadiga::default();
+        for _ in 0..10 {
+            assert_eq!(d.registrar(&fim(-0.4, 1000), 0.4, sem_fadiga), Some(true));
+        }
+        let p = d.parametros();
+        assert!(p.fracao_amplitude > inicial.fracao_amplitude);
+        assert!(p.tempo_sustentacao_ms > inicial.tempo_sustentacao_ms);
+        assert!(p.intervalo_ms < inicial.intervalo_ms);
+
+        //amplitude caindo pela metade: fadiga, o intervalo sobe e a exigência cai
+        let fadiga = AlertasFadiga {
+            fadiga: true,
+            queda_amplitude: 0.5,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            d.registrar(&fim(-0.2, 1000), 0.4, fadiga);
+        }
+        let dicas = d.dicas();
+        assert!((dicas.fadiga - 0.5).abs() < 1e-6);
+        assert_eq!(dicas.taxa_sucesso, 0.0);
+        assert!(dicas.parametros.intervalo_ms > p.intervalo_ms);
+        assert!(dicas.parametros.fracao_amplitude < p.fracao_amplitude);
+
+        d.nova_sessao();
+        assert_eq!(
+            (d.dicas().tentativas, d.parametros()),
+            (0, dicas.parametros)
+        );
+        let mut inicio = fim(-0.4, 1000);
+        inicio.tipo = TipoEvento::Inicio;
+        assert_eq!(d.registrar(&inicio, 0.4, sem_fadiga), None);
+
+        //sem o alerta de AnaliseFadiga, a queda de amplitude não aumenta o intervalo
+        let mut d = Dificuldade::default();
+        for _ in 0..10 {
+            d.registrar(&fim(-0.2, 1000), 0.4, sem_fadiga);
+        }
+        assert_eq!(d.dicas().fadiga, 0.0);
+        assert_eq!(d.parametros().intervalo_ms, inicial.intervalo_ms);
+    }
+}
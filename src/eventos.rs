@@ -0,0 +1,174 @@
+/*
+Eventos de movimento a partir das previsões suavizadas de um pé: início, sustentação (repetida
+enquanto o movimento se mantém) e fim, com duração e ângulos de pico. Repouso não gera eventos;
+trocar de um movimento direto para outro gera o fim do primeiro e o início do segundo.
+*/
+
+use crate::{dado_papete::DadoPapete, movimento::Movimento};
+
+use std::time::{Duration, Instant};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TipoEvento {
+    Inicio,
+    Sustentacao,
+    Fim,
+}
+
+/*
+instante_ms é contado a partir da criação do detector (a da Papete, para os eventos dela).
+duracao_ms é o tempo desde o início do movimento; os picos são os ângulos corrigidos (rad)
+de maior módulo até o evento.
+*/
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct EventoMovimento {
+    pub tipo: TipoEvento,
+    pub lado_esq: bool,
+    pub movimento: Movimento,
+    pub instante_ms: u64,
+    pub duracao_ms: u64,
+    pub pico_pitch: f32,
+    pub pico_roll: f32,
+}
+
+#[derive(Clone, Debug)]
+struct Ativo {
+    movimento: Movimento,
+    inicio: Instant,
+    ultima_sustentacao: Instant,
+    pico_pitch: f32,
+    pico_roll: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct DetectorEventos {
+    origem: Instant,
+    //de quanto em quanto tempo um movimento mantido gera Sustentacao
+    pub intervalo_sustentacao: Duration,
+    ativo: Option<Ativo>,
+}
+
+impl Default for DetectorEventos {
+    fn default() -> Self {
+        DetectorEventos::new(Instant::now(), Duration::from_millis(500))
+    }
+}
+
+impl DetectorEventos {
+    pub fn new(origem: Instant, intervalo_sustentacao: Duration) -> DetectorEventos {
+        DetectorEventos {
+            origem,
+            intervalo_sustentacao,
+            ativo: None,
+        }
+    }
+
+    /*
+    Recebe o movimento suavizado e o dado corrigido (sem offset) do mesmo instante
+    */
+    pub fn atualizar(
+        &mut self,
+        movimento: Movimento,
+        dado: &DadoPapete,
+        agora: Instant,
+    ) -> Vec<EventoMovimento> {
+        let mut eventos = Vec::new();
+        if let Some(ativo) = &mut self.ativo {
+            if ativo.movimento == movimento {
+                ativo.pico_pitch = maior_modulo(ativo.pico_pitch, dado.pitch);
+                ativo.pico_roll = maior_modulo(ativo.pico_roll, dado.roll);
+                if agora.duration_since(ativo.ultima_sustentacao) >= self.intervalo_sustentacao {
+                    ativo.ultima_sustentacao = agora;
+                    eventos.push(self.evento(TipoEvento::Sustentacao, dado.lado_esq, agora));
+                }
+                return eventos;
+            }
+            eventos.push(self.evento(TipoEvento::Fim, dado.lado_esq, agora));
+            self.ativo = None;
+        }
+        if movimento != Movimento::Repouso {
+            self.ativo = Some(Ativo {
+                movimento,
+                inicio: agora,
+                ultima_sustentacao: agora,
+                pico_pitch: dado.pitch,
+                pico_roll: dado.roll,
+            });
+            eventos.push(self.evento(TipoEvento::Inicio, dado.lado_esq, agora));
+        }
+        eventos
+    }
+
+    /*
+    Movimento em andamento, se houver
+    */
+    #[allow(dead_code)]
+    pub fn ativo(&self) -> Option<Movimento> {
+        self.ativo.as_ref().map(|a| a.movimento)
+    }
+
+    fn evento(&self, tipo: TipoEvento, lado_esq: bool, agora: Instant) -> EventoMovimento {
+        let ativo = self.ativo.as_ref().unwrap();
+        EventoMovimento {
+            tipo,
+            lado_esq,
+            movimento: ativo.movimento,
+            instante_ms: agora.duration_since(self.origem).as_millis() as u64,
+            duracao_ms: agora.duration_since(ativo.inicio).as_millis() as u64,
+            pico_pitch: ativo.pico_pitch,
+            pico_roll: ativo.pico_roll,
+        }
+    }
+}
+
+fn maior_modulo(a: f32, b: f32) -> f32 {
+    if b.abs() > a.abs() {
+        b
+    } else {
+        a
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::eventos::*;
+
+    #[test]
+    fn inicio_sustentacao_fim() {
+        use Movimento::*;
+        let origem = Instant::now();
+        let mut detector = DetectorEventos::new(origem, Duration::from_millis(500));
+        let mut passo = |ms: u64, movimento: Movimento, pitch: f32| {
+            let dado = DadoPapete::basico(pitch, 0.0, true);
+            detector.atualizar(movimento, &dado, origem + Duration::from_millis(ms))
+        };
+
+        assert!(passo(0, Repouso, 0.0).is_empty());
+        let e = passo(100, Dorsiflexao, 0.2);
+        assert_eq!(
+            (e[0].tipo, e[0].movimento, e[0].instante_ms),
+            (TipoEvento::Inicio, Dorsiflexao, 100)
+        );
+        assert!(passo(300, Dorsiflexao, 0.4).is_empty());
+        let e = passo(600, Dorsiflexao, 0.3);
+        assert_eq!((e[0].tipo, e[0].duracao_ms), (TipoEvento::Sustentacao, 500));
+
+        //troca direta: fim de um e início do outro
+        let e = passo(700, Flexao, -0.1);
+        assert_eq!(e.len(), 2);
+        assert_eq!(
+            (e[0].tipo, e[0].movimento, e[0].duracao_ms),
+            (TipoEvento::Fim, Dorsiflexao, 600)
+        );
+        assert_eq!(e[0].pico_pitch, 0.4);
+        assert_eq!((e[1].tipo, e[1].movimento), (TipoEvento::Inicio, Flexao));
+
+        let e = passo(800, Repouso, 0.0);
+        assert_eq!(
+            (e[0].tipo, e[0].duracao_ms, e[0].pico_pitch),
+            (TipoEvento::Fim, 100, -0.1)
+        );
+    }
+}
@@ -0,0 +1,233 @@
+/*
+Contagem de repetições de um exercício prescrito em séries, como 3x10 dorsiflexões.
+Uma repetição vale quando o pé sai do repouso para o movimento pedido e volta ao repouso,
+alcançando a amplitude mínima e ficando fora do repouso pelo menos duracao_min.
+Passar para outro movimento no meio invalida a tentativa, e só volta a contar depois do repouso.
+*/
+
+use crate::{
+    calibracao::distancia,
+    dado_papete::DadoPapete,
+    lado::{Lado, PorLado},
+    movimento::Movimento,
+};
+
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+pub struct ConfigExercicio {
+    pub movimento: Movimento,
+    //pé que faz o exercício; None conta os dois
+    pub lado: Option<Lado>,
+    pub series: u32,
+    pub repeticoes: u32,
+    pub amplitude_min: f32,
+    pub duracao_min: Duration,
+}
+
+impl ConfigExercicio {
+    pub fn new(movimento: Movimento, series: u32, repeticoes: u32) -> ConfigExercicio {
+        ConfigExercicio {
+            movimento,
+            lado: None,
+            series,
+            repeticoes,
+            amplitude_min: 0.1,
+            duracao_min: Duration::from_millis(300),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Repeticao {
+    //série em que foi feita, começando em 0
+    pub serie: u32,
+    pub lado: Lado,
+    //distância máxima do repouso (rad)
+    pub amplitude: f32,
+    pub duracao: Duration,
+}
+
+/*
+Progresso para os jogos. serie começa em 1 e repeticao conta as da série atual.
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ProgressoExercicio {
+    pub serie: u32,
+    pub series: u32,
+    pub repeticao: u32,
+    pub repeticoes: u32,
+    pub total: u32,
+    pub invalidas: u32,
+    pub concluido: bool,
+    pub ultima_amplitude: f32,
+    pub ultima_duracao_ms: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+enum EstadoRepeticao {
+    //esperando o pé voltar ao repouso
+    #[default]
+    Desarmado,
+    Repouso,
+    Movendo {
+        inicio: Instant,
+        pico: f32,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct Exercicio {
+    config: ConfigExercicio,
+    estados: PorLado<EstadoRepeticao>,
+    feitas: Vec<Repeticao>,
+    invalidas: u32,
+}
+
+impl Exercicio {
+    pub fn new(config: ConfigExercicio) -> Exercicio {
+        Exercicio {
+            config,
+            estados: PorLado::default(),
+            feitas: Vec::new(),
+            invalidas: 0,
+        }
+    }
+
+    /*
+    Recebe o movimento suavizado e o dado corrigido de um pé.
+    Retorna a repetição, se esta amostra completou uma.
+    */
+    pub fn atualizar(
+        &mut self,
+        movimento: Movimento,
+        dado: &DadoPapete,
+        agora: Instant,
+    ) -> Option<Repeticao> {
+        let lado = dado.lado();
+        if self.concluido() || matches!(self.config.lado, Some(l) if l != lado) {
+            return None;
+        }
+        let estado = self.estados[lado];
+        let (novo, completa) = match estado {
+            EstadoRepeticao::Movendo { inicio, pico } => {
+                if movimento == self.config.movimento {
+                    let pico = pico.max(distancia(dado));
+                    (EstadoRepeticao::Movendo { inicio, pico }, None)
+                } else if movimento == Movimento::Repouso {
+                    let repeticao = Repeticao {
+                        serie: self.feitas.len() as u32 / self.config.repeticoes.max(1),
+                        lado,
+                        amplitude: pico,
+                        duracao: agora.duration_since(inicio),
+                    };
+                    (EstadoRepeticao::Repouso, Some(repeticao))
+                } else {
+                    self.invalidas += 1;
+                    (EstadoRepeticao::Desarmado, None)
+                }
+            }
+            _ if movimento == Movimento::Repouso => (EstadoRepeticao::Repouso, None),
+            EstadoRepeticao::Repouso if movimento == self.config.movimento => (
+                EstadoRepeticao::Movendo {
+                    inicio: agora,
+                    pico: distancia(dado),
+                },
+                None,
+            ),
+            _ => (EstadoRepeticao::Desarmado, None),
+        };
+        self.estados[lado] = novo;
+
+        let repeticao = completa?;
+        if repeticao.amplitude < self.config.amplitude_min
+            || repeticao.duracao < self.config.duracao_min
+        {
+            self.invalidas += 1;
+            return None;
+        }
+        self.feitas.push(repeticao);
+        Some(repeticao)
+    }
+
+    pub fn concluido(&self) -> bool {
+        self.feitas.len() as u32 >= self.config.series * self.config.repeticoes
+    }
+
+    pub fn repeticoes(&self) -> &[Repeticao] {
+        &self.feitas
+    }
+
+    pub fn config(&self) -> &ConfigExercicio {
+        &self.config
+    }
+
+    pub fn progresso(&self) -> ProgressoExercicio {
+        let total = self.feitas.len() as u32;
+        let por_serie = self.config.repeticoes.max(1);
+        let concluido = self.concluido();
+        let (serie, repeticao) = if concluido {
+            (self.config.series, self.config.repeticoes)
+        } else {
+            (total / por_serie + 1, total % por_serie)
+        };
+        let ultima = self.feitas.last();
+        ProgressoExercicio {
+            serie,
+            series: self.config.series,
+            repeticao,
+            repeticoes: self.config.repeticoes,
+            total,
+            invalidas: self.invalidas,
+            concluido,
+            ultima_amplitude: ultima.map_or(0.0, |r| r.amplitude),
+            ultima_duracao_ms: ultima.map_or(0, |r| r.duracao.as_millis() as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::exercicio::*;
+
+    #[test]
+    fn conta_series() {
+        use Movimento::*;
+        let mut exercicio = Exercicio::new(ConfigExercicio::new(Dorsiflexao, 2, 2));
+        let origem = Instant::now();
+        let mut passo = |ms: u64, movimento: Movimento, pitch: f32| {
+            let dado = DadoPapete::basico(pitch, 0.0, true);
+            exercicio.atualizar(movimento, &dado, origem + Duration::from_millis(ms))
+        };
+
+        //começou fora do repouso: não conta até voltar
+        assert_eq!(passo(0, Dorsiflexao, 0.3), None);
+        assert_eq!(passo(100, Repouso, 0.0), None);
+        //válida
+        passo(200, Dorsiflexao, 0.2);
+        passo(400, Dorsiflexao, 0.4);
+        let r = passo(600, Repouso, 0.0).unwrap();
+        assert_eq!((r.serie, r.amplitude), (0, 0.4));
+        assert_eq!(r.duracao, Duration::from_millis(400));
+        //curta demais, pequena demais e trocando de movimento
+        passo(700, Dorsiflexao, 0.3);
+        assert_eq!(passo(800, Repouso, 0.0), None);
+        passo(900, Dorsiflexao, 0.05);
+        assert_eq!(passo(1300, Repouso, 0.0), None);
+        passo(1400, Dorsiflexao, 0.3);
+        passo(1500, Eversao, 0.3);
+        assert_eq!(passo(2000, Dorsiflexao, 0.3), None);
+        assert_eq!(passo(2500, Repouso, 0.0), None);
+
+        for i in 0..3 {
+            let t = 3000 + i * 1000;
+            passo(t, Dorsiflexao, 0.3);
+            assert!(passo(t + 500, Repouso, 0.0).is_some());
+        }
+        let p = exercicio.progresso();
+        assert!(p.concluido);
+        assert_eq!((p.serie, p.repeticao, p.total, p.invalidas), (2, 2, 4, 3));
+        assert_eq!(exercicio.repeticoes()[2].serie, 1);
+    }
+}
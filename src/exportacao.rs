@@ -0,0 +1,633 @@
+/*
+Exporta resultados de avaliação (de um modelo, da comparação entre modelos e da curva de
+transferência) em JSON e num relatório HTML autocontido, para arquivar junto de cada versão
+do modelo.
+Também exporta a evolução do arco de movimento de um paciente, em HTML e CSV (separado por ;).
+*/
+
+use crate::{
+    arco::ArcoMovimento,
+    avaliacao::{CurvaTransferencia, MatrizConfusao, RelatorioAvaliacao},
+    comparacao::Comparacao,
+    lado::Lado,
+    movimento::Movimento,
+    perfil::Perfil,
+};
+
+use serde::Serialize;
+use std::fmt::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Metadados {
+    pub modelo: String,
+    pub versao_biblioteca: String,
+    //segundos desde UNIX_EPOCH
+    pub gerado_em: u64,
+    pub dataset: String,
+    pub validacao: String,
+    pub treino: String,
+}
+
+impl Metadados {
+    pub fn new(modelo: &str, dataset: &str, validacao: String, treino: String) -> Metadados {
+        Metadados {
+            modelo: modelo.to_string(),
+            versao_biblioteca: env!("CARGO_PKG_VERSION").to_string(),
+            gerado_em: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_secs(),
+            dataset: dataset.to_string(),
+            validacao,
+            treino,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Exportado<'a, T> {
+    metadados: &'a Metadados,
+    relatorio: &'a T,
+}
+
+/*
+relatorio é um RelatorioAvaliacao, uma Comparacao ou uma CurvaTransferencia
+*/
+pub fn gerar_json<T: Serialize>(relatorio: &T, metadados: &Metadados) -> String {
+    serde_json::to_string_pretty(&Exportado {
+        metadados,
+        relatorio,
+    })
+    .unwrap()
+}
+
+pub fn salvar_json<T: Serialize>(
+    destino: &str,
+    relatorio: &T,
+    metadados: &Metadados,
+) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_json(relatorio, metadados))
+}
+
+pub fn salvar_html(
+    destino: &str,
+    relatorio: &RelatorioAvaliacao,
+    metadados: &Metadados,
+) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_html(relatorio, metadados))
+}
+
+pub fn salvar_html_comparacao(
+    destino: &str,
+    comparacao: &Comparacao,
+    metadados: &Metadados,
+) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_html_comparacao(comparacao, metadados))
+}
+
+pub fn salvar_html_curva(
+    destino: &str,
+    curva: &CurvaTransferencia,
+    metadados: &Metadados,
+) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_html_curva(curva, metadados))
+}
+
+pub fn gerar_html(relatorio: &RelatorioAvaliacao, metadados: &Metadados) -> String {
+    let mut html = String::new();
+    cabecalho_html(&mut html, "Avaliação", metadados);
+
+    html.push_str("<h2>Resumo</h2>\n<table>\n");
+    let r = relatorio;
+    for (nome, valor) in [
+        (
+            "acurácia",
+            format!(
+                "{:.4} (IC95% {:.4} a {:.4})",
+                r.acuracia, r.ic_acuracia.0, r.ic_acuracia.1
+            ),
+        ),
+        (
+            "acurácia balanceada",
+            format!("{:.4}", r.acuracia_balanceada),
+        ),
+        ("kappa", format!("{:.4}", r.kappa)),
+        (
+            "macro precisão / revocação / F1",
+            format!(
+                "{:.4} / {:.4} / {:.4}",
+                r.macro_precisao, r.macro_revocacao, r.macro_f1
+            ),
+        ),
+        ("micro F1", format!("{:.4}", r.micro_f1)),
+        (
+            "média das pastas",
+            format!(
+                "{:.4} ± {:.4} (IC95% {:.4} a {:.4})",
+                r.media_pastas, r.desvio_pastas, r.ic_media_pastas.0, r.ic_media_pastas.1
+            ),
+        ),
+    ] {
+        linha(&mut html, "th", &[nome.to_string(), valor]);
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Matriz de confusão</h2>\n");
+    tabela_matriz(&mut html, &relatorio.matriz);
+
+    html.push_str("<h2>Por movimento</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &["movimento", "precisão", "revocação", "F1", "n"].map(String::from),
+    );
+    for m in Movimento::todos() {
+        let c = &relatorio.por_classe[m.as_usize()];
+        linha(
+            &mut html,
+            "td",
+            &[
+                m.str_completa().to_string(),
+                format!("{:.4}", c.precisao),
+                format!("{:.4}", c.revocacao),
+                format!("{:.4}", c.f1),
+                c.suporte.to_string(),
+            ],
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Pastas</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &["pasta", "acurácia", "grupos de teste"].map(String::from),
+    );
+    for (k, taxa) in relatorio.taxas_pastas.iter().enumerate() {
+        let grupos = relatorio
+            .grupos_pastas
+            .get(k)
+            .map(|g| format!("{:?}", g))
+            .unwrap_or_default();
+        linha(
+            &mut html,
+            "td",
+            &[(k + 1).to_string(), format!("{:.4}", taxa), grupos],
+        );
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+/*
+Um resumo por modelo, os testes pareados e a matriz de cada modelo
+*/
+pub fn gerar_html_comparacao(comparacao: &Comparacao, metadados: &Metadados) -> String {
+    let mut html = String::new();
+    cabecalho_html(&mut html, "Comparação", metadados);
+
+    html.push_str("<h2>Modelos</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &[
+            "modelo",
+            "acurácia",
+            "IC95%",
+            "acurácia balanceada",
+            "F1 macro",
+            "kappa",
+            "treino (s)",
+            "latência (µs)",
+        ]
+        .map(String::from),
+    );
+    for m in &comparacao.modelos {
+        let r = &m.relatorio;
+        linha(
+            &mut html,
+            "td",
+            &[
+                escapar(&m.nome),
+                format!("{:.4}", r.acuracia),
+                format!("{:.4} a {:.4}", r.ic_acuracia.0, r.ic_acuracia.1),
+                format!("{:.4}", r.acuracia_balanceada),
+                format!("{:.4}", r.macro_f1),
+                format!("{:.4}", r.kappa),
+                format!("{:.3}", m.tempo_treino_s),
+                format!("{:.1}", m.latencia_us),
+            ],
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Testes pareados</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &[
+            "A",
+            "B",
+            "só A acertou",
+            "só B acertou",
+            "χ² McNemar",
+            "p McNemar",
+            "t pareado",
+            "graus de liberdade",
+            "t significativo (5%)",
+        ]
+        .map(String::from),
+    );
+    for p in &comparacao.pares {
+        linha(
+            &mut html,
+            "td",
+            &[
+                escapar(&p.modelo_a),
+                escapar(&p.modelo_b),
+                p.so_a.to_string(),
+                p.so_b.to_string(),
+                format!("{:.3}", p.qui_quadrado),
+                format!("{:.4}", p.p_mcnemar),
+                format!("{:.3}", p.t),
+                p.graus_liberdade.to_string(),
+                if p.t_significativo { "sim" } else { "não" }.to_string(),
+            ],
+        );
+    }
+    html.push_str("</table>\n");
+
+    for m in &comparacao.modelos {
+        writeln!(html, "<h2>Matriz de confusão - {}</h2>", escapar(&m.nome)).unwrap();
+        tabela_matriz(&mut html, &m.relatorio.matriz);
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+/*
+Acurácia média antes e depois da transferência para cada N, e o detalhe de cada sessão
+*/
+pub fn gerar_html_curva(curva: &CurvaTransferencia, metadados: &Metadados) -> String {
+    let mut html = String::new();
+    cabecalho_html(&mut html, "Transferência", metadados);
+
+    html.push_str("<h2>Curva</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &["amostras por movimento", "antes", "depois", "desvio depois"].map(String::from),
+    );
+    for p in &curva.pontos {
+        linha(
+            &mut html,
+            "td",
+            &[
+                p.amostras_por_movimento.to_string(),
+                format!("{:.4}", p.acuracia_antes),
+                format!("{:.4}", p.acuracia_depois),
+                format!("{:.4}", p.desvio_depois),
+            ],
+        );
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Por sessão</h2>\n<table>\n");
+    linha(
+        &mut html,
+        "th",
+        &["amostras por movimento", "sessão", "antes", "depois"].map(String::from),
+    );
+    for p in &curva.pontos {
+        for (sessao, antes, depois) in &p.por_sessao {
+            linha(
+                &mut html,
+                "td",
+                &[
+                    p.amostras_por_movimento.to_string(),
+                    sessao.to_string(),
+                    format!("{:.4}", antes),
+                    format!("{:.4}", depois),
+                ],
+            );
+        }
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+static MOVIMENTOS_ARCO: [Movimento; 4] = [
+    Movimento::Dorsiflexao,
+    Movimento::Flexao,
+    Movimento::Eversao,
+    Movimento::Inversao,
+];
+
+/*
+Lê o perfil e grava <prefixo>.html e <prefixo>.csv
+*/
+pub fn exportar_arco(
+    endereco_perfil: &str,
+    prefixo: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let perfil = Perfil::carregar(endereco_perfil)?;
+    salvar_html_arco(&format!("{}.html", prefixo), &perfil)?;
+    salvar_csv_arco(&format!("{}.csv", prefixo), &perfil)?;
+    Ok(())
+}
+
+pub fn salvar_html_arco(destino: &str, perfil: &Perfil) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_html_arco(perfil))
+}
+
+pub fn salvar_csv_arco(destino: &str, perfil: &Perfil) -> std::io::Result<()> {
+    std::fs::write(destino, gerar_csv_arco(perfil))
+}
+
+/*
+Uma linha por sessão, em ordem cronológica, com o arco (graus) de cada pé em cada direção
+e os alertas de fadiga e compensação da sessão
+*/
+pub fn gerar_html_arco(perfil: &Perfil) -> String {
+    let mut html = String::new();
+    abrir_html(
+        &mut html,
+        &format!("Arco de movimento - {}", escapar(&perfil.id)),
+    );
+
+    let cabecalho = || {
+        let mut colunas = vec![String::new()];
+        for lado in ["E", "D"] {
+            for m in MOVIMENTOS_ARCO {
+                colunas.push(format!("{} {}", m.str_completa(), lado));
+            }
+        }
+        colunas
+    };
+    let valores = |esq: &ArcoMovimento, dir: &ArcoMovimento| {
+        [esq, dir]
+            .iter()
+            .flat_map(|arco| MOVIMENTOS_ARCO.map(|m| format!("{:.1}", arco.graus(m))))
+            .collect::<Vec<String>>()
+    };
+
+    html.push_str("<h2>Melhor arco (graus)</h2>\n<table>\n");
+    linha(&mut html, "th", &cabecalho());
+    let mut melhor = vec!["todas as sessões".to_string()];
+    melhor.extend(valores(
+        &perfil.arco_maximo(Lado::Esquerdo),
+        &perfil.arco_maximo(Lado::Direito),
+    ));
+    linha(&mut html, "td", &melhor);
+    html.push_str("</table>\n");
+
+    html.push_str("<h2>Por sessão (graus)</h2>\n<table>\n");
+    let mut colunas = cabecalho();
+    colunas[0] = "data".to_string();
+    colunas.push("alertas".to_string());
+    linha(&mut html, "th", &colunas);
+    for registro in sessoes(perfil) {
+        let mut celulas = vec![data(registro.data)];
+        celulas.extend(valores(&registro.arco_esq, &registro.arco_dir));
+        let alertas: Vec<&str> = [
+            (registro.fadiga, "fadiga"),
+            (registro.compensacao, "compensação"),
+        ]
+        .iter()
+        .filter(|(houve, _)| *houve)
+        .map(|(_, nome)| *nome)
+        .collect();
+        celulas.push(alertas.join(", "));
+        linha(&mut html, "td", &celulas);
+    }
+    html.push_str("</table>\n</body>\n</html>\n");
+    html
+}
+
+pub fn gerar_csv_arco(perfil: &Perfil) -> String {
+    let mut csv = String::from("sessao;data;lado");
+    for m in MOVIMENTOS_ARCO {
+        write!(csv, ";{}", m.str_simplificada()).unwrap();
+    }
+    csv.push_str(";fadiga;compensacao\n");
+    for registro in sessoes(perfil) {
+        for (lado, arco) in [("E", &registro.arco_esq), ("D", &registro.arco_dir)] {
+            write!(csv, "{};{};{}", registro.sessao, data(registro.data), lado).unwrap();
+            for m in MOVIMENTOS_ARCO {
+                write!(csv, ";{:.2}", arco.graus(m)).unwrap();
+            }
+            writeln!(
+                csv,
+                ";{};{}",
+                registro.fadiga as u8, registro.compensacao as u8
+            )
+            .unwrap();
+        }
+    }
+    csv
+}
+
+fn sessoes(perfil: &Perfil) -> Vec<&crate::perfil::RegistroSessao> {
+    let mut sessoes: Vec<_> = perfil.historico.iter().collect();
+    sessoes.sort_by_key(|r| r.data);
+    sessoes
+}
+
+/*
+Data (AAAA-MM-DD, UTC) de segundos desde UNIX_EPOCH, pelo civil_from_days de Howard Hinnant
+*/
+fn data(segundos: u64) -> String {
+    let dias = (segundos / 86400) as i64 + 719468;
+    let era = dias / 146097;
+    let dia_era = dias - era * 146097;
+    let ano_era = (dia_era - dia_era / 1460 + dia_era / 36524 - dia_era / 146096) / 365;
+    let dia_ano = dia_era - (365 * ano_era + ano_era / 4 - ano_era / 100);
+    let mes_p = (5 * dia_ano + 2) / 153;
+    let dia = dia_ano - (153 * mes_p + 2) / 5 + 1;
+    let mes = if mes_p < 10 { mes_p + 3 } else { mes_p - 9 };
+    let ano = ano_era + era * 400 + if mes <= 2 { 1 } else { 0 };
+    format!("{:04}-{:02}-{:02}", ano, mes, dia)
+}
+
+/*
+Início do documento, com o título (já escapado) na aba e no h1; comum a todas as páginas
+*/
+fn abrir_html(html: &mut String, titulo: &str) {
+    writeln!(
+        html,
+        "<!DOCTYPE html>\n<html lang=\"pt-BR\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}</style>\n</head>\n<body>\n<h1>{}</h1>",
+        titulo, ESTILO, titulo
+    )
+    .unwrap();
+}
+
+/*
+Abre o documento com o título e a tabela de metadados
+*/
+fn cabecalho_html(html: &mut String, tipo: &str, metadados: &Metadados) {
+    abrir_html(html, &format!("{} - {}", tipo, escapar(&metadados.modelo)));
+
+    html.push_str("<h2>Modelo</h2>\n<table>\n");
+    for (nome, valor) in [
+        ("modelo", metadados.modelo.clone()),
+        ("versão da biblioteca", metadados.versao_biblioteca.clone()),
+        ("gerado em (unix)", metadados.gerado_em.to_string()),
+        ("dataset", metadados.dataset.clone()),
+        ("validação", metadados.validacao.clone()),
+        ("treino", metadados.treino.clone()),
+    ] {
+        linha(html, "th", &[nome.to_string(), escapar(&valor)]);
+    }
+    html.push_str("</table>\n");
+}
+
+/*
+A cor de cada célula é proporcional à fração da linha (movimento esperado),
+para que movimentos com poucas amostras também fiquem visíveis
+*/
+fn tabela_matriz(html: &mut String, matriz: &MatrizConfusao) {
+    html.push_str("<table class=\"matriz\">\n");
+    let mut cabecalho = vec!["esperado \\ obtido".to_string()];
+    cabecalho.extend(
+        Movimento::todos()
+            .iter()
+            .map(|m| m.str_completa().to_string()),
+    );
+    cabecalho.push("n".to_string());
+    linha(html, "th", &cabecalho);
+    let suporte = matriz.suporte();
+    for m in Movimento::todos() {
+        let i = m.as_usize();
+        write!(html, "<tr><th>{}</th>", m.str_completa()).unwrap();
+        for valor in matriz.valores()[i] {
+            let fracao = if suporte[i] > 0 {
+                valor as f32 / suporte[i] as f32
+            } else {
+                0.0
+            };
+            write!(
+                html,
+                "<td style=\"background: rgba(31, 119, 180, {:.3}); color: {}\" title=\"{:.1}%\">{}</td>",
+                fracao,
+                if fracao > 0.5 { "white" } else { "black" },
+                fracao * 100.0,
+                valor
+            )
+            .unwrap();
+        }
+        writeln!(html, "<td>{}</td></tr>", suporte[i]).unwrap();
+    }
+    html.push_str("</table>\n");
+}
+
+static ESTILO: &str = "body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.7em; text-align: right; }
+th { background: #f4f4f4; text-align: left; }
+.matriz td { min-width: 4em; text-align: center; }
+";
+
+fn linha(html: &mut String, celula: &str, valores: &[String]) {
+    html.push_str("<tr>");
+    for v in valores {
+        write!(html, "<{}>{}</{}>", celula, v, celula).unwrap();
+    }
+    html.push_str("</tr>\n");
+}
+
+fn escapar(texto: &str) -> String {
+    texto
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::arvore::Arvore;
+    use crate::avaliacao::{ConfigValidacao, Estrategia};
+    use crate::comparacao::{comparar, construtor};
+    use crate::dado_papete::DadoPapete;
+    use crate::exportacao::*;
+
+    fn relatorio() -> RelatorioAvaliacao {
+        use Movimento::*;
+        let esperado = [Repouso, Repouso, Flexao, Flexao];
+        let obtido = [Repouso, Flexao, Flexao, Flexao];
+        RelatorioAvaliacao::new(
+            MatrizConfusao::new(esperado.into_iter(), obtido.into_iter()),
+            vec![0.75],
+        )
+    }
+
+    #[test]
+    fn escapa_rotulos_e_metadados() {
+        let metadados = Metadados::new(
+            "<script>alert(1)</script>",
+            "a&b.csv",
+            "\"sessao\"".to_string(),
+            "<i>".to_string(),
+        );
+        let html = gerar_html(&relatorio(), &metadados);
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+        assert!(html.contains("a&amp;b.csv"));
+        assert!(html.contains("&quot;sessao&quot;"));
+        assert!(html.contains("&lt;i&gt;"));
+        assert!(!html.contains("<script>") && !html.contains("<i>"));
+
+        let dados: Vec<DadoPapete> = (0..12)
+            .map(|i| {
+                let movimento = if i % 2 == 0 {
+                    Movimento::Repouso
+                } else {
+                    Movimento::Flexao
+                };
+                DadoPapete::completo(-0.3 * (i % 2) as f32, 0.0, true, movimento, i % 2 + i / 6)
+            })
+            .collect();
+        let comparacao = comparar(
+            &dados,
+            &[
+                ("<b>arvore</b>", construtor::<Arvore>()),
+                ("a&b", construtor::<Arvore>()),
+            ],
+            &ConfigValidacao {
+                estrategia: Estrategia::Sessao,
+                pastas: None,
+                seed: 0,
+            },
+            &Default::default(),
+        )
+        .unwrap();
+        let html = gerar_html_comparacao(&comparacao, &metadados);
+        assert!(html.contains("&lt;b&gt;arvore&lt;/b&gt;"));
+        assert!(html.contains("a&amp;b"));
+        assert!(!html.contains("<b>") && !html.contains("<script>"));
+    }
+
+    #[test]
+    fn formato_json() {
+        let metadados = Metadados::new("arvore", "papete.csv", String::new(), String::new());
+        let json: serde_json::Value =
+            serde_json::from_str(&gerar_json(&relatorio(), &metadados)).unwrap();
+        assert_eq!(json["metadados"]["modelo"], "arvore");
+
+        //por_classe e matriz indexados pelo nome do movimento
+        let r = &json["relatorio"];
+        assert_eq!(r["acuracia"], 0.75);
+        let por_classe = r["por_classe"].as_object().unwrap();
+        assert_eq!(por_classe.len(), 5);
+        assert_eq!(por_classe["Flexao"]["suporte"], 2);
+        assert_eq!(por_classe["Repouso"]["revocacao"], 0.5);
+        let matriz = r["matriz"].as_object().unwrap();
+        assert_eq!(matriz.len(), 5);
+        assert!(matriz
+            .values()
+            .all(|linha| linha.as_object().unwrap().len() == 5));
+        assert_eq!(matriz["Repouso"]["Flexao"], 1);
+        assert_eq!(matriz["Flexao"]["Flexao"], 2);
+        assert_eq!(matriz["Flexao"]["Repouso"], 0);
+    }
+}
@@ -0,0 +1,371 @@
+/*
+Fadiga e compensação ao longo da sessão. Cada movimento de cada pé, do início à volta ao repouso
+(pela previsão suavizada), vira uma tentativa com amplitude de pico, tempo até o pico, direção no
+pico e quanto da tentativa a previsão bruta passou em outro movimento. As últimas tentativas de cada
+pé e movimento são comparadas com a referência:
+- fadiga: a amplitude cai ou o tempo até o pico aumenta;
+- compensação: a direção desvia da esperada, ou o pé escorrega para outro movimento (como uma
+  inversão quando se pede flexão plantar).
+A referência de amplitude e direção vem das amostras com que o previsor foi adaptado (transferir);
+sem elas, das primeiras tentativas da sessão. A do tempo até o pico vem sempre do começo da sessão.
+*/
+
+use crate::{
+    calibracao::distancia,
+    dado_papete::DadoPapete,
+    lado::{Lado, PorLado},
+    movimento::Movimento,
+    suavizacao::Previsao,
+};
+
+use std::time::{Duration, Instant};
+
+/*
+janela: tentativas de cada pé e movimento que formam a referência do começo da sessão e o recente.
+Os outros campos são os limiares dos alertas.
+*/
+#[derive(Clone, Debug)]
+pub struct ConfigFadiga {
+    pub janela: usize,
+    pub queda_amplitude: f32,
+    pub lentidao: f32,
+    pub desvio_graus: f32,
+    pub fracao_outra_classe: f32,
+}
+
+impl Default for ConfigFadiga {
+    fn default() -> Self {
+        ConfigFadiga {
+            janela: 5,
+            queda_amplitude: 0.2,
+            lentidao: 0.3,
+            desvio_graus: 20.0,
+            fracao_outra_classe: 0.3,
+        }
+    }
+}
+
+/*
+Pior caso entre os pés e movimentos com tentativas suficientes.
+queda_amplitude e lentidao são frações (0.2 = 20% menor / mais lento); desvio_graus é o ângulo
+entre a direção recente e a esperada. deriva é o movimento para o qual o pé mais escorregou,
+válido se tem_deriva.
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AlertasFadiga {
+    pub fadiga: bool,
+    pub compensacao: bool,
+    pub queda_amplitude: f32,
+    pub lentidao: f32,
+    pub desvio_graus: f32,
+    pub fracao_outra_classe: f32,
+    pub tem_deriva: bool,
+    pub deriva: Movimento,
+}
+
+impl Default for AlertasFadiga {
+    fn default() -> Self {
+        AlertasFadiga {
+            fadiga: false,
+            compensacao: false,
+            queda_amplitude: 0.0,
+            lentidao: 0.0,
+            desvio_graus: 0.0,
+            fracao_outra_classe: 0.0,
+            tem_deriva: false,
+            deriva: Movimento::Repouso,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tentativa {
+    pub movimento: Movimento,
+    pub lado: Lado,
+    pub amplitude: f32,
+    pub tempo_ate_pico: Duration,
+    //(pitch, roll) unitário no pico
+    pub direcao: (f32, f32),
+    //fração das amostras em que a previsão bruta era outro movimento, e o mais frequente deles
+    pub fracao_outra_classe: f32,
+    pub outra_classe: Option<Movimento>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Referencia {
+    amplitude: f32,
+    direcao: (f32, f32),
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Ativo {
+    movimento: Movimento,
+    inicio: Instant,
+    pico: DadoPapete,
+    instante_pico: Instant,
+    amostras: u32,
+    outras: [u32; 5],
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct AnaliseFadiga {
+    config: ConfigFadiga,
+    linha_base: PorLado<[Option<Referencia>; 5]>,
+    ativos: PorLado<Option<Ativo>>,
+    tentativas: Vec<Tentativa>,
+    alertas: AlertasFadiga,
+    //se os alertas apareceram em algum momento da sessão
+    houve_fadiga: bool,
+    houve_compensacao: bool,
+}
+
+impl AnaliseFadiga {
+    /*
+    Troca os limiares e a janela, mantendo a linha de base e as tentativas da sessão
+    */
+    pub fn configurar(&mut self, config: ConfigFadiga) {
+        self.config = config;
+        self.alertas = self.calcular_alertas();
+    }
+
+    /*
+    Referência a partir de amostras rotuladas e corrigidas, como as usadas no retreino.
+    A amplitude esperada é o percentil 90 das distâncias ao repouso.
+    */
+    pub fn definir_linha_base(&mut self, amostras: &[DadoPapete]) {
+        for (lado, referencias) in self.linha_base.iter_mut() {
+            for m in Movimento::todos() {
+                let do_movimento: Vec<&DadoPapete> = amostras
+                    .iter()
+                    .filter(|d| d.lado() == lado && d.movimento == Some(m))
+                    .collect();
+                referencias[m.as_usize()] = if m == Movimento::Repouso || do_movimento.is_empty() {
+                    None
+                } else {
+                    let mut distancias: Vec<f32> =
+                        do_movimento.iter().map(|d| distancia(d)).collect();
+                    distancias.sort_by(f32::total_cmp);
+                    let pitch = do_movimento.iter().map(|d| d.pitch).sum::<f32>();
+                    let roll = do_movimento.iter().map(|d| d.roll).sum::<f32>();
+                    Some(Referencia {
+                        amplitude: distancias[(distancias.len() - 1) * 9 / 10],
+                        direcao: unitario(pitch, roll),
+                    })
+                };
+            }
+        }
+    }
+
+    /*
+    Recebe a previsão e o dado corrigido de um pé. Retorna a tentativa, se esta amostra terminou uma.
+    */
+    pub fn atualizar(
+        &mut self,
+        previsao: Previsao,
+        dado: &DadoPapete,
+        agora: Instant,
+    ) -> Option<Tentativa> {
+        let lado = dado.lado();
+        let mut terminada = None;
+        match &mut self.ativos[lado] {
+            Some(ativo) if ativo.movimento == previsao.suavizado => {
+                ativo.amostras += 1;
+                if previsao.bruto != ativo.movimento && previsao.bruto != Movimento::Repouso {
+                    ativo.outras[previsao.bruto.as_usize()] += 1;
+                }
+                if distancia(dado) > distancia(&ativo.pico) {
+                    ativo.pico = *dado;
+                    ativo.instante_pico = agora;
+                }
+                return None;
+            }
+            Some(ativo) => terminada = Some(*ativo),
+            None => {}
+        }
+        self.ativos[lado] = (previsao.suavizado != Movimento::Repouso).then_some(Ativo {
+            movimento: previsao.suavizado,
+            inicio: agora,
+            pico: *dado,
+            instante_pico: agora,
+            amostras: 1,
+            outras: [0; 5],
+        });
+
+        let ativo = terminada?;
+        let (outra, vezes) = Movimento::todos()
+            .iter()
+            .map(|m| (*m, ativo.outras[m.as_usize()]))
+            .max_by_key(|(_, vezes)| *vezes)
+            .unwrap();
+        let tentativa = Tentativa {
+            movimento: ativo.movimento,
+            lado,
+            amplitude: distancia(&ativo.pico),
+            tempo_ate_pico: ativo.instante_pico.duration_since(ativo.inicio),
+            direcao: unitario(ativo.pico.pitch, ativo.pico.roll),
+            fracao_outra_classe: vezes as f32 / ativo.amostras as f32,
+            outra_classe: (vezes > 0).then_some(outra),
+        };
+        self.tentativas.push(tentativa);
+        self.alertas = self.calcular_alertas();
+        self.houve_fadiga |= self.alertas.fadiga;
+        self.houve_compensacao |= self.alertas.compensacao;
+        Some(tentativa)
+    }
+
+    fn calcular_alertas(&self) -> AlertasFadiga {
+        let n = self.config.janela.max(1);
+        let mut alertas = AlertasFadiga::default();
+        let mut derivas = [0usize; 5];
+        for lado in Lado::todos() {
+            for m in Movimento::todos() {
+                let grupo: Vec<&Tentativa> = self
+                    .tentativas
+                    .iter()
+                    .filter(|t| t.lado == lado && t.movimento == m)
+                    .collect();
+                let base = self.linha_base[lado][m.as_usize()];
+                //sem linha de base, o começo e o recente não podem se sobrepor
+                if grupo.len() < n || (base.is_none() && grupo.len() < 2 * n) {
+                    continue;
+                }
+                let (inicio, recente) = (&grupo[..n], &grupo[grupo.len() - n..]);
+                let referencia = base.unwrap_or_else(|| Referencia {
+                    amplitude: media(inicio, |t| t.amplitude),
+                    direcao: unitario(
+                        media(inicio, |t| t.direcao.0),
+                        media(inicio, |t| t.direcao.1),
+                    ),
+                });
+
+                let queda = 1.0 - media(recente, |t| t.amplitude) / referencia.amplitude;
+                alertas.queda_amplitude = alertas.queda_amplitude.max(queda);
+                if grupo.len() >= 2 * n {
+                    let antes = media(inicio, |t| t.tempo_ate_pico.as_secs_f32());
+                    let agora = media(recente, |t| t.tempo_ate_pico.as_secs_f32());
+                    if antes > 0.0 {
+                        alertas.lentidao = alertas.lentidao.max(agora / antes - 1.0);
+                    }
+                }
+                let direcao = unitario(
+                    media(recente, |t| t.direcao.0),
+                    media(recente, |t| t.direcao.1),
+                );
+                let cosseno = direcao.0 * referencia.direcao.0 + direcao.1 * referencia.direcao.1;
+                let desvio = cosseno.clamp(-1.0, 1.0).acos().to_degrees();
+                alertas.desvio_graus = alertas.desvio_graus.max(desvio);
+                let outra = media(recente, |t| t.fracao_outra_classe);
+                alertas.fracao_outra_classe = alertas.fracao_outra_classe.max(outra);
+                for t in recente {
+                    if let Some(outra) = t.outra_classe {
+                        derivas[outra.as_usize()] += 1;
+                    }
+                }
+            }
+        }
+        let c = &self.config;
+        alertas.fadiga =
+            alertas.queda_amplitude >= c.queda_amplitude || alertas.lentidao >= c.lentidao;
+        alertas.compensacao = alertas.desvio_graus >= c.desvio_graus
+            || alertas.fracao_outra_classe >= c.fracao_outra_classe;
+        if let Some(m) = Movimento::todos()
+            .into_iter()
+            .filter(|m| derivas[m.as_usize()] > 0)
+            .max_by_key(|m| derivas[m.as_usize()])
+        {
+            alertas.tem_deriva = true;
+            alertas.deriva = m;
+        }
+        alertas
+    }
+
+    pub fn alertas(&self) -> AlertasFadiga {
+        self.alertas
+    }
+
+    /*
+    Se houve fadiga e compensação em algum momento da sessão
+    */
+    pub fn resumo_sessao(&self) -> (bool, bool) {
+        (self.houve_fadiga, self.houve_compensacao)
+    }
+
+    #[allow(dead_code)]
+    pub fn tentativas(&self) -> &[Tentativa] {
+        &self.tentativas
+    }
+
+    /*
+    Descarta as tentativas, mantendo a linha de base
+    */
+    pub fn nova_sessao(&mut self) {
+        *self = AnaliseFadiga {
+            config: self.config.clone(),
+            linha_base: self.linha_base,
+            ..Default::default()
+        };
+    }
+}
+
+fn media(tentativas: &[&Tentativa], valor: impl Fn(&Tentativa) -> f32) -> f32 {
+    tentativas.iter().map(|t| valor(t)).sum::<f32>() / tentativas.len().max(1) as f32
+}
+
+fn unitario(pitch: f32, roll: f32) -> (f32, f32) {
+    let norma = (pitch * pitch + roll * roll).sqrt();
+    if norma > 0.0 {
+        (pitch / norma, roll / norma)
+    } else {
+        (0.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fadiga::*;
+
+    #[test]
+    fn queda_e_deriva() {
+        use Movimento::*;
+        let mut analise = AnaliseFadiga::default();
+        //flexão plantar do pé esquerdo: pitch positivo
+        let base: Vec<DadoPapete> = (0..10)
+            .map(|i| DadoPapete::completo(0.3 + 0.01 * i as f32, 0.0, true, Flexao, 1))
+            .collect();
+        analise.definir_linha_base(&base);
+
+        let origem = Instant::now();
+        let mut t = 0;
+        let mut tentativa =
+            |analise: &mut AnaliseFadiga, pitch: f32, roll: f32, bruto: Movimento| {
+                let mut passo = |bruto: Movimento, suavizado: Movimento, dado: DadoPapete| {
+                    t += 100;
+                    let agora = origem + Duration::from_millis(t);
+                    analise.atualizar(Previsao { bruto, suavizado }, &dado, agora)
+                };
+                passo(Flexao, Flexao, DadoPapete::basico(0.1, 0.0, true));
+                passo(bruto, Flexao, DadoPapete::basico(pitch, roll, true));
+                passo(Repouso, Repouso, DadoPapete::basico(0.0, 0.0, true)).unwrap()
+            };
+
+        for _ in 0..5 {
+            let r = tentativa(&mut analise, 0.39, 0.0, Flexao);
+            assert_eq!(r.tempo_ate_pico, Duration::from_millis(100));
+        }
+        assert_eq!(analise.alertas(), AlertasFadiga::default());
+
+        //menor e puxando para a inversão (roll negativo no pé esquerdo)
+        for _ in 0..5 {
+            tentativa(&mut analise, 0.2, -0.2, Inversao);
+        }
+        let alertas = analise.alertas();
+        assert!(alertas.fadiga && alertas.compensacao);
+        assert!(alertas.queda_amplitude > 0.2 && alertas.desvio_graus > 40.0);
+        assert_eq!((alertas.tem_deriva, alertas.deriva), (true, Inversao));
+
+        analise.nova_sessao();
+        assert_eq!(analise.resumo_sessao(), (false, false));
+        assert!(analise.tentativas().is_empty());
+    }
+}
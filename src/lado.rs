@@ -0,0 +1,88 @@
+/*
+Identidade do pé. Todo estado que existe uma vez por pé fica num PorLado, indexado por Lado,
+em vez de tuplas em que a posição de cada pé precisa ser lembrada.
+*/
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Index, IndexMut};
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Lado {
+    Esquerdo,
+    Direito,
+}
+
+impl Lado {
+    pub fn todos() -> [Lado; 2] {
+        [Lado::Esquerdo, Lado::Direito]
+    }
+
+    pub fn de_esq(lado_esq: bool) -> Lado {
+        if lado_esq {
+            Lado::Esquerdo
+        } else {
+            Lado::Direito
+        }
+    }
+
+    /*
+    Lado da papete pelo nome do dispositivo; outros dispositivos (como as luvas) não têm lado de pé
+    */
+    pub fn de_dispositivo(dispositivo: &str) -> Option<Lado> {
+        match dispositivo {
+            "papE" => Some(Lado::Esquerdo),
+            "papD" => Some(Lado::Direito),
+            _ => None,
+        }
+    }
+
+    pub fn esq(&self) -> bool {
+        *self == Lado::Esquerdo
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PorLado<T> {
+    pub esq: T,
+    pub dir: T,
+}
+
+impl<T> PorLado<T> {
+    pub fn new(esq: T, dir: T) -> PorLado<T> {
+        PorLado { esq, dir }
+    }
+
+    #[allow(dead_code)]
+    pub fn iter(&self) -> impl Iterator<Item = (Lado, &T)> {
+        [(Lado::Esquerdo, &self.esq), (Lado::Direito, &self.dir)].into_iter()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Lado, &mut T)> {
+        [
+            (Lado::Esquerdo, &mut self.esq),
+            (Lado::Direito, &mut self.dir),
+        ]
+        .into_iter()
+    }
+}
+
+impl<T> Index<Lado> for PorLado<T> {
+    type Output = T;
+
+    fn index(&self, lado: Lado) -> &T {
+        match lado {
+            Lado::Esquerdo => &self.esq,
+            Lado::Direito => &self.dir,
+        }
+    }
+}
+
+impl<T> IndexMut<Lado> for PorLado<T> {
+    fn index_mut(&mut self, lado: Lado) -> &mut T {
+        match lado {
+            Lado::Esquerdo => &mut self.esq,
+            Lado::Direito => &mut self.dir,
+        }
+    }
+}
@@ -1,23 +1,51 @@
+pub mod arco;
 pub mod arvore;
+pub mod aumento;
 pub mod avaliacao;
+pub mod balanceamento;
+pub mod bilateral;
+pub mod calibracao;
+pub mod caracteristicas;
+pub mod comparacao;
 pub mod conexao;
+pub mod controle;
 pub mod csv_helper;
 pub mod dado_papete;
+pub mod dificuldade;
+pub mod eventos;
+pub mod exercicio;
+pub mod exportacao;
+pub mod fadiga;
+pub mod lado;
 pub mod movimento;
 pub mod neural;
 pub mod papete;
+pub mod perfil;
 pub mod previsor;
+pub mod suavizacao;
 
 pub mod comm;
 
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
 use std::os::raw::c_char;
 
+use arco::ArcoMovimento;
+
+use bilateral::MovimentoBilateral;
+use calibracao::{ConfigCalibracao, EtapaCalibracao, QualidadeOffset};
+use comm::orientacao::{ConfigOrientacao, Orientacao, TipoFiltro};
 use conexao::Conexao;
+use controle::{ConfigControle, Controle};
+use dificuldade::DicasDificuldade;
+use eventos::EventoMovimento;
+use exercicio::{ConfigExercicio, ProgressoExercicio};
+use fadiga::{AlertasFadiga, ConfigFadiga};
+use lado::Lado;
 use movimento::Movimento;
-use neural::Neural;
-use papete::Papete;
-use previsor::Previsor;
+use neural::{ConfigTransferencia, Neural};
+use papete::{EstadoRetreino, Papete};
+use previsor::{Previsor, ResultadoTransferencia};
+use suavizacao::{ConfigSuavizacao, Previsao};
 
 extern crate simple_error;
 
@@ -135,6 +163,61 @@ pub unsafe extern "C" fn obter_movimento(s: *mut Papete) -> Movimento {
     (*s).obter_movimento()
 }
 
+/*
+Mesma amostra de obter_movimento, com a previsão bruta junto da suavizada
+*/
+#[no_mangle]
+pub unsafe extern "C" fn obter_previsao(s: *mut Papete) -> Previsao {
+    (*s).obter_previsao()
+}
+
+/*
+Suavização das previsões: janela do voto da maioria, frações dos votos para um movimento
+assumir e para o atual se manter, e tempo mínimo (ms) antes de trocar.
+janela 1 e permanencia_ms 0 desligam a suavização.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn configurar_suavizacao(
+    s: *mut Papete,
+    janela: u32,
+    limiar_entrada: f32,
+    limiar_saida: f32,
+    permanencia_ms: u32,
+) {
+    (*s).configurar_suavizacao(ConfigSuavizacao {
+        janela: janela as usize,
+        limiar_entrada,
+        limiar_saida,
+        permanencia_minima: std::time::Duration::from_millis(permanencia_ms as u64),
+    });
+}
+
+/*
+Copia até capacidade eventos pendentes, do mais antigo ao mais novo, para buffer e os
+retira da fila. Retorna quantos foram copiados; se for igual a capacidade, pode haver mais.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn obter_eventos(
+    s: *mut Papete,
+    buffer: *mut EventoMovimento,
+    capacidade: usize,
+) -> i32 {
+    let mut n = 0;
+    for evento in (*s).drenar_eventos(capacidade) {
+        *(buffer.add(n)) = evento;
+        n += 1;
+    }
+    n as i32
+}
+
+/*
+Movimento de cada pé e o gesto combinado dos dois
+*/
+#[no_mangle]
+pub unsafe extern "C" fn obter_movimento_bilateral(s: *mut Papete) -> MovimentoBilateral {
+    (*s).obter_movimento_bilateral()
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn ativar_modo_conexao_imediata(s: *mut Papete) {
     (*s).ativar_modo_conexao_imediata(1);
@@ -162,6 +245,104 @@ pub unsafe extern "C" fn obter_rotacao(s: *mut Papete) -> Vec2 {
     }
 }
 
+/*
+Inclinação do pé de -1 a 1 em cada eixo, escalada pela amplitude da calibração.
+pitch positivo é dorsiflexão e roll positivo é eversão, nos dois pés. Zero se o pé não tem dados.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn obter_controle(s: *mut Papete, lado_esq: bool) -> Controle {
+    (*s).obter_controle(Lado::de_esq(lado_esq)).unwrap_or_default()
+}
+
+/*
+zona_morta: fração da amplitude que ainda vale 0; expoente: 1 para resposta linear,
+maior para suavizar perto do repouso
+*/
+#[no_mangle]
+pub unsafe extern "C" fn configurar_controle(s: *mut Papete, zona_morta: f32, expoente: f32) {
+    (*s).configurar_controle(ConfigControle {
+        zona_morta,
+        expoente,
+        ..Default::default()
+    });
+}
+
+/*
+Amplitude (fração da calibrada), sustentação e intervalo sugeridos para o próximo estímulo,
+com a taxa de sucesso, a amplitude média e a fadiga das últimas tentativas.
+Só é atualizado enquanto o jogo chama obter_movimento ou obter_movimento_bilateral.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn dicas_dificuldade(s: *mut Papete) -> DicasDificuldade {
+    (*s).dicas_dificuldade()
+}
+
+/*
+Alertas de fadiga (amplitude caindo, movimentos mais lentos) e de compensação (direção desviando,
+pé escorregando para outro movimento) das últimas tentativas da sessão
+*/
+#[no_mangle]
+pub unsafe extern "C" fn alertas_fadiga(s: *mut Papete) -> AlertasFadiga {
+    (*s).alertas_fadiga()
+}
+
+/*
+janela: tentativas de cada pé e movimento comparadas; queda_amplitude e lentidao: frações
+a partir das quais há fadiga; desvio_graus e fracao_outra_classe: a partir das quais há compensação.
+Também muda quando as dicas de dificuldade consideram fadiga.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn configurar_fadiga(
+    s: *mut Papete,
+    janela: u32,
+    queda_amplitude: f32,
+    lentidao: f32,
+    desvio_graus: f32,
+    fracao_outra_classe: f32,
+) {
+    (*s).configurar_fadiga(ConfigFadiga {
+        janela: janela as usize,
+        queda_amplitude,
+        lentidao,
+        desvio_graus,
+        fracao_outra_classe,
+    });
+}
+
+/*
+Pitch, roll e yaw (rad) do filtro de orientação do pé, sem descontar o repouso.
+Zero se o pé não está conectado ou não manda o giroscópio; o yaw deriva com o tempo.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn obter_orientacao(s: *mut Papete, lado_esq: bool) -> Orientacao {
+    (*s).orientacao(Lado::de_esq(lado_esq)).unwrap_or_default()
+}
+
+/*
+madgwick: usa o filtro Madgwick em vez do complementar. ganho é o alfa do complementar
+(peso do giroscópio, de 0 a 1) ou o beta do Madgwick. tolerancia_acel: fração da gravidade
+que o módulo da aceleração pode variar para o acelerômetro ainda corrigir os ângulos.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn configurar_orientacao(
+    s: *mut Papete,
+    madgwick: bool,
+    ganho: f32,
+    tolerancia_acel: f32,
+) {
+    let mut config = ConfigOrientacao {
+        tolerancia_acel,
+        ..Default::default()
+    };
+    if madgwick {
+        config.filtro = TipoFiltro::Madgwick;
+        config.beta = ganho;
+    } else {
+        config.alfa = ganho;
+    }
+    (*s).configurar_orientacao(config);
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn obter_lado(s: *mut Papete) -> bool {
     match (*s).obter_dados_qqr() {
@@ -188,11 +369,283 @@ pub unsafe extern "C" fn zerar(s: *mut Papete) -> i32 {
     0
 }
 
+/*
+Inicia o retreino em segundo plano e retorna imediatamente.
+O andamento é consultado com estado_retreino e progresso_retreino. Quando o estado chega em
+AguardandoConfirmacao, o modelo adaptado só passa a ser usado depois de manter_retreino.
+*/
 #[no_mangle]
 pub unsafe extern "C" fn retreinar(s: *mut Papete) -> bool {
-    if (*s).registrados.len() > 0 {
-        (*s).transferir(&(*s).registrados);
-        return true;
+    (*s).iniciar_retreino()
+}
+
+/*
+Configura os próximos retreinos. endereco_populacao é um CSV no formato de papete.csv de onde
+saem qtd_replay amostras misturadas à calibração (nulo para não usar replay);
+peso_regularizacao puxa os pesos de volta aos de antes do retreino.
+Retorna false se o CSV não pôde ser lido.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn configurar_transferencia(
+    s: *mut Papete,
+    endereco_populacao: *const c_char,
+    qtd_replay: u32,
+    peso_regularizacao: f32,
+    iteracoes: u32,
+) -> bool {
+    let populacao = if endereco_populacao.is_null() {
+        Vec::new()
+    } else {
+        match CStr::from_ptr(endereco_populacao).to_str() {
+            Ok(endereco) => match csv_helper::carregar_dados(endereco) {
+                Ok(dados) => dados,
+                Err(e) => {
+                    eprintln!("Falha ao ler população: {}", e);
+                    return false;
+                }
+            },
+            Err(_) => return false,
+        }
+    };
+    let config = ConfigTransferencia {
+        qtd_replay: qtd_replay as usize,
+        peso_regularizacao: peso_regularizacao as f64,
+        iteracoes: iteracoes as usize,
+        ..Default::default()
+    };
+    (*s).configurar_transferencia(&config, &populacao);
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn estado_retreino(s: *mut Papete) -> EstadoRetreino {
+    (*s).estado_retreino()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn progresso_retreino(s: *mut Papete) -> f32 {
+    (*s).progresso_retreino()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cancelar_retreino(s: *mut Papete) {
+    (*s).cancelar_retreino();
+}
+
+/*
+Passa a usar o modelo adaptado. Retorna false se nenhum está aguardando confirmação.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn manter_retreino(s: *mut Papete) -> bool {
+    (*s).manter_retreino()
+}
+
+/*
+Descarta o modelo adaptado e continua com o atual
+*/
+#[no_mangle]
+pub unsafe extern "C" fn descartar_retreino(s: *mut Papete) {
+    (*s).descartar_retreino();
+}
+
+/*
+Acurácia na calibração antes e depois do último retreino que terminou, mantido ou não
+(ou -1 se não há)
+*/
+#[no_mangle]
+pub unsafe extern "C" fn resultado_retreino(s: *mut Papete) -> ResultadoTransferencia {
+    (*s).resultado_retreino().unwrap_or(ResultadoTransferencia {
+        acuracia_antes: -1.0,
+        acuracia_depois: -1.0,
+    })
+}
+
+/*
+Começa a calibração guiada com a configuração padrão.
+A cada quadro o jogo chama atualizar_calibracao e mostra instrucao_calibracao.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn iniciar_calibracao(s: *mut Papete) {
+    (*s).iniciar_calibracao(ConfigCalibracao::default());
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn atualizar_calibracao(s: *mut Papete) -> EtapaCalibracao {
+    (*s).atualizar_calibracao()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn progresso_calibracao(s: *mut Papete) -> f32 {
+    (*s).calibracao().map(|c| c.progresso()).unwrap_or(0.0)
+}
+
+/*
+Movimento pedido agora (como em Movimento), ou -1 se não há
+*/
+#[no_mangle]
+pub unsafe extern "C" fn movimento_calibracao(s: *mut Papete) -> i32 {
+    match (*s).calibracao().and_then(|c| c.movimento_atual()) {
+        Some(movimento) => movimento.as_usize() as i32,
+        None => -1,
+    }
+}
+
+/*
+Copia a instrução atual, em UTF-8 terminada em null, para array_ptr com até capacidade bytes.
+Retorna quantos bytes foram ocupados, sem contar o null.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn instrucao_calibracao(
+    s: *mut Papete,
+    array_ptr: *mut u8,
+    capacidade: usize,
+) -> i32 {
+    if capacidade == 0 {
+        return 0;
+    }
+    let instrucao = match (*s).calibracao() {
+        Some(c) => c.instrucao(),
+        None => String::new(),
+    };
+    let mut tamanho = instrucao.len().min(capacidade - 1);
+    //não corta um caractere no meio
+    while !instrucao.is_char_boundary(tamanho) {
+        tamanho -= 1;
+    }
+    std::ptr::copy_nonoverlapping(instrucao.as_ptr(), array_ptr, tamanho);
+    *(array_ptr.add(tamanho)) = 0;
+    tamanho as i32
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn cancelar_calibracao(s: *mut Papete) {
+    (*s).cancelar_calibracao();
+}
+
+/*
+Começa um exercício de series x repeticoes do movimento. lado: 0 esquerdo, 1 direito, -1 qualquer um.
+amplitude_min (rad) e duracao_min_ms definem uma repetição válida.
+Retorna false se o movimento for inválido ou Repouso.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn iniciar_exercicio(
+    s: *mut Papete,
+    movimento: i32,
+    lado: i32,
+    series: u32,
+    repeticoes: u32,
+    amplitude_min: f32,
+    duracao_min_ms: u32,
+) -> bool {
+    let movimento = match Movimento::try_from(movimento) {
+        Ok(Movimento::Repouso) | Err(_) => return false,
+        Ok(m) => m,
+    };
+    let mut config = ConfigExercicio::new(movimento, series, repeticoes);
+    config.lado = match lado {
+        0 => Some(Lado::Esquerdo),
+        1 => Some(Lado::Direito),
+        _ => None,
+    };
+    config.amplitude_min = amplitude_min;
+    config.duracao_min = std::time::Duration::from_millis(duracao_min_ms as u64);
+    (*s).iniciar_exercicio(config);
+    true
+}
+
+/*
+Progresso do exercício atual; tudo zerado se não houver um
+*/
+#[no_mangle]
+pub unsafe extern "C" fn progresso_exercicio(s: *mut Papete) -> ProgressoExercicio {
+    (*s).progresso_exercicio().unwrap_or_default()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn encerrar_exercicio(s: *mut Papete) {
+    (*s).encerrar_exercicio();
+}
+
+/*
+Descarta os offsets de repouso; eles voltam a ser capturados com os pés parados
+nas próximas chamadas de obter_movimento
+*/
+#[no_mangle]
+pub unsafe extern "C" fn recalibrar_repouso(s: *mut Papete) {
+    (*s).recalibrar_repouso();
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn qualidade_offset(s: *mut Papete, lado_esq: bool) -> QualidadeOffset {
+    (*s).qualidade_offset(Lado::de_esq(lado_esq))
+}
+
+/*
+Salva o perfil do paciente (id) em destino; o previsor adaptado, se houver, vai para <destino>.modelo.
+Retorna false em caso de erro.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn salvar_perfil(
+    s: *mut Papete,
+    id: *const c_char,
+    destino: *const c_char,
+) -> bool {
+    match (CStr::from_ptr(id).to_str(), CStr::from_ptr(destino).to_str()) {
+        (Ok(id), Ok(destino)) => match (*s).salvar_perfil(id, destino) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Falha ao salvar perfil: {}", e);
+                false
+            }
+        },
+        _ => false,
+    }
+}
+
+/*
+Maiores ângulos (graus) do pé em cada direção desde o início da sessão
+*/
+#[no_mangle]
+pub unsafe extern "C" fn arco_movimento(s: *mut Papete, lado_esq: bool) -> ArcoMovimento {
+    (*s).arco_movimento(Lado::de_esq(lado_esq))
+}
+
+/*
+Relatório da evolução do arco de movimento do perfil salvo em endereco_perfil,
+gravado em <prefixo>.html e <prefixo>.csv. Retorna false em caso de erro.
+*/
+#[no_mangle]
+pub unsafe extern "C" fn exportar_arco(
+    endereco_perfil: *const c_char,
+    prefixo: *const c_char,
+) -> bool {
+    match (
+        CStr::from_ptr(endereco_perfil).to_str(),
+        CStr::from_ptr(prefixo).to_str(),
+    ) {
+        (Ok(endereco_perfil), Ok(prefixo)) => {
+            match exportacao::exportar_arco(endereco_perfil, prefixo) {
+                Ok(_) => true,
+                Err(e) => {
+                    eprintln!("Falha ao exportar arco de movimento: {}", e);
+                    false
+                }
+            }
+        }
+        _ => false,
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn carregar_perfil(s: *mut Papete, endereco: *const c_char) -> bool {
+    match CStr::from_ptr(endereco).to_str() {
+        Ok(endereco) => match (*s).carregar_perfil(endereco) {
+            Ok(_) => true,
+            Err(e) => {
+                eprintln!("Falha ao carregar perfil: {}", e);
+                false
+            }
+        },
+        Err(_) => false,
     }
-    false
 }
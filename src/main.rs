@@ -3,21 +3,48 @@ O executavel serve para testes ou pra coleta de testes, como uma CLI.
 O que vai fazer depende dos argumentos passados:
 -coleta [-1 ou -2]
 -teste -arvore
+-teste -dinamica
 -teste -neural
 -teste -neural -transferencia
--aval -arvore
--aval -neural
+-teste -calibracao
+-teste -bilateral
+-teste -exercicio
+-teste -controle
+-teste -orientacao
+-aval -arvore [opções]
+-aval -neural [opções]
+-aval -todos [opções]
+-aval -transferencia
+  opções: balanceado, reamostrado, sessao, pastas=N, seed=N, relatorio=prefixo,
+  replay=N (só em -aval -transferencia)
+-arco [perfil.json] [prefixo]
 */
 
+mod arco;
 mod arvore;
+mod aumento;
 mod avaliacao;
+mod balanceamento;
+mod bilateral;
+mod calibracao;
+mod caracteristicas;
+mod comparacao;
 mod conexao;
+mod controle;
 mod csv_helper;
 mod dado_papete;
+mod dificuldade;
+mod eventos;
+mod exercicio;
+mod exportacao;
+mod fadiga;
+mod lado;
 mod movimento;
 mod neural;
 mod papete;
+mod perfil;
 mod previsor;
+mod suavizacao;
 
 mod comm;
 
@@ -25,12 +52,24 @@ extern crate rand;
 extern crate statistical;
 
 use arvore::Arvore;
+use aumento::ConfigAumento;
+use avaliacao::{ConfigValidacao, Estrategia};
+use balanceamento::{ConfigTreino, PesoClasses};
+use calibracao::{ConfigCalibracao, EtapaCalibracao};
+use caracteristicas::EspecCaracteristicas;
+use comm::orientacao::{ConfigOrientacao, TipoFiltro};
+use controle::ConfigControle;
 use dado_papete::DadoPapete;
+use exercicio::ConfigExercicio;
+use exportacao::Metadados;
+use lado::Lado;
 use movimento::Movimento;
-use neural::Neural;
-use papete::Papete;
+use neural::{ConfigTransferencia, Neural};
+use papete::{EstadoRetreino, Papete};
 use previsor::Previsor;
 
+use serde::Serialize;
+
 use std::{
     io::{self, Write},
     thread, time,
@@ -52,11 +91,11 @@ fn teste_serial() {
     println!("Encontradas!");
     loop {
         let dados = papete.obter_dados();
-        if dados.0.is_some() {
-            print!("{}\t", dados.0.unwrap());
+        if dados.esq.is_some() {
+            print!("{}\t", dados.esq.unwrap());
         }
-        if dados.1.is_some() {
-            print!("{}", dados.1.unwrap());
+        if dados.dir.is_some() {
+            print!("{}", dados.dir.unwrap());
         }
         println!("");
     }
@@ -82,10 +121,14 @@ fn teste_reconhecimento(){
 
 /*
 Procura a qtd especificada de papetes (1 ou 2). Quando encontra, ela pede cada um dos movimentos
-no numero especificado de rodadas. O usuario precisa confirmar estar pronto antes de cada movimento.
+no numero especificado de rodadas. O usuario confirma estar pronto no começo de cada rodada; dali
+em diante a gravação é contínua, alternando repouso e movimento, para que as transições também
+fiquem no dataset com o instante de cada amostra.
 */
 fn coleta(papetes: usize, rodadas: usize) -> Vec<DadoPapete> {
-    let intervalo = time::Duration::from_millis(80);
+    let intervalo = time::Duration::from_millis(50);
+    //amostras de cada etapa (2 s)
+    let amostras_etapa = 40;
 
     let mut papete = Papete::new();
     papete.ativar_modo_conexao_imediata(papetes);
@@ -99,18 +142,23 @@ fn coleta(papetes: usize, rodadas: usize) -> Vec<DadoPapete> {
 
     for rodada in 0..rodadas {
         println!("{}/{}", rodada + 1, rodadas);
+        println!("{:?}", papete.obter_conexoes());
+        //aguarda input qqr
+        io::stdin().read_line(&mut String::new()).unwrap();
+        let mut etapas = vec![Movimento::Repouso];
         for movimento in [
-            Movimento::Repouso,
             Movimento::Dorsiflexao,
             Movimento::Flexao,
             Movimento::Eversao,
             Movimento::Inversao,
         ] {
-            println!("{:?}", papete.obter_conexoes());
+            etapas.push(movimento);
+            etapas.push(Movimento::Repouso);
+        }
+        for (i, movimento) in etapas.into_iter().enumerate() {
+            println!("");
             println!("{}: {}", movimento.str_completa(), movimento.descricao());
-            //aguarda input qqr
-            io::stdin().read_line(&mut String::new()).unwrap();
-            for _ in 0..10 {
+            for _ in 0..amostras_etapa {
                 if papete.obter_conexoes().len() < papetes {
                     println!("Papete desconectou");
                     while papete.obter_conexoes().len() < papetes {
@@ -122,13 +170,165 @@ fn coleta(papetes: usize, rodadas: usize) -> Vec<DadoPapete> {
                 io::stdout().flush().unwrap();
                 thread::sleep(intervalo);
             }
-            println!("");
-            //println!("{:?}",papete.registrados);
+            if i == 0 {
+                println!("");
+                for lado in Lado::todos() {
+                    println!("offset {:?}: {:?}", lado, papete.qualidade_offset(lado));
+                }
+            }
         }
+        println!("");
     }
     std::mem::replace(&mut papete.registrados, Vec::new())
 }
 
+/*
+Conecta com as duas papetes e imprime o movimento de cada pé e o gesto combinado, em loop.
+*/
+fn teste_bilateral() {
+    let intervalo = time::Duration::from_millis(50);
+    let mut papete = Papete::com_previsor(Box::new(Neural::carregar("papete.pt").unwrap()));
+    println!("Procurando papetes...");
+    while papete.obter_conexoes().len() < 2 {
+        thread::sleep(intervalo);
+    }
+    println!("Encontradas!");
+    loop {
+        println!("{}", papete.obter_movimento_bilateral());
+        for evento in papete.drenar_eventos(usize::MAX) {
+            println!(
+                "  {:?} {} ({}): {} ms, pico {:.2}/{:.2}",
+                evento.tipo,
+                evento.movimento,
+                if evento.lado_esq { "E" } else { "D" },
+                evento.duracao_ms,
+                evento.pico_pitch,
+                evento.pico_roll
+            );
+        }
+        thread::sleep(intervalo);
+    }
+}
+
+/*
+Conta uma série de 3x10 dorsiflexões com qualquer pé, como um jogo faria, e lista as repetições.
+*/
+fn teste_exercicio() {
+    let intervalo = time::Duration::from_millis(50);
+    let mut papete = Papete::com_previsor(Box::new(Neural::carregar("papete.pt").unwrap()));
+    println!("Procurando papetes...");
+    while papete.obter_conexoes().is_empty() {
+        thread::sleep(intervalo);
+    }
+    papete.iniciar_sessao(0);
+    papete.iniciar_exercicio(ConfigExercicio::new(Movimento::Dorsiflexao, 3, 10));
+    let mut total = 0;
+    while !papete.exercicio().unwrap().concluido() {
+        papete.obter_movimento_bilateral();
+        let progresso = papete.progresso_exercicio().unwrap();
+        if progresso.total != total {
+            total = progresso.total;
+            println!(
+                "Série {}/{}: {}/{} ({:.2} rad, {} ms)",
+                progresso.serie,
+                progresso.series,
+                progresso.repeticao,
+                progresso.repeticoes,
+                progresso.ultima_amplitude,
+                progresso.ultima_duracao_ms
+            );
+            let dicas = papete.dicas_dificuldade();
+            println!(
+                "  próxima: {:.0}% da amplitude, {} ms sustentando, {} ms de intervalo (sucesso {:.0}%, fadiga {:.2})",
+                dicas.parametros.fracao_amplitude * 100.0,
+                dicas.parametros.tempo_sustentacao_ms,
+                dicas.parametros.intervalo_ms,
+                dicas.taxa_sucesso * 100.0,
+                dicas.fadiga
+            );
+            let alertas = papete.alertas_fadiga();
+            if alertas.fadiga {
+                println!("  Atenção: sinais de fadiga");
+            }
+            if alertas.compensacao {
+                if alertas.tem_deriva {
+                    println!("  Atenção: compensando com {}", alertas.deriva);
+                } else {
+                    println!("  Atenção: compensando");
+                }
+            }
+        }
+        thread::sleep(intervalo);
+    }
+    let exercicio = papete.encerrar_exercicio().unwrap();
+    println!("{} concluída:", exercicio.config().movimento);
+    for repeticao in exercicio.repeticoes() {
+        println!(
+            "  série {} {:?}: {:.2} rad em {:?}",
+            repeticao.serie + 1,
+            repeticao.lado,
+            repeticao.amplitude,
+            repeticao.duracao
+        );
+    }
+    for lado in Lado::todos() {
+        println!("Arco {:?} (graus): {:?}", lado, papete.arco_movimento(lado));
+    }
+}
+
+/*
+Imprime o controle contínuo de cada pé, com resposta quadrática, em loop.
+Sem calibração, a escala é a amplitude padrão.
+*/
+fn teste_controle() {
+    let intervalo = time::Duration::from_millis(50);
+    let mut papete = Papete::new();
+    papete.configurar_controle(ConfigControle {
+        expoente: 2.0,
+        ..Default::default()
+    });
+    papete.iniciar_sessao(0);
+    loop {
+        for lado in Lado::todos() {
+            match papete.obter_controle(lado) {
+                Some(c) => print!("{:?}: {:+.2} {:+.2}\t", lado, c.pitch, c.roll),
+                None => print!("{:?}: -\t", lado),
+            }
+        }
+        println!();
+        thread::sleep(intervalo);
+    }
+}
+
+/*
+Imprime pitch, roll e yaw do filtro Madgwick de cada pé, em graus, em loop.
+Precisa do firmware que manda o giroscópio.
+*/
+fn teste_orientacao() {
+    let intervalo = time::Duration::from_millis(50);
+    let mut papete = Papete::new();
+    papete.configurar_orientacao(ConfigOrientacao {
+        filtro: TipoFiltro::Madgwick,
+        ..Default::default()
+    });
+    loop {
+        for lado in Lado::todos() {
+            match papete.orientacao(lado) {
+                Some(o) => print!(
+                    "{:?}: {:+6.1} {:+6.1} {:+6.1}\t",
+                    lado,
+                    o.pitch.to_degrees(),
+                    o.roll.to_degrees(),
+                    o.yaw.to_degrees()
+                ),
+                None => print!("{:?}: -\t", lado),
+            }
+        }
+        println!();
+        thread::sleep(intervalo);
+    }
+}
+
 /*
 Conecta com a papete e imprime o que a arvore esta prevendo, em loop.
 */
@@ -150,7 +350,53 @@ fn teste_arvore() {
     papete.iniciar_sessao(0);
 
     loop {
-        println!("{}", papete.obter_movimento());
+        let previsao = papete.obter_previsao();
+        println!("{}\t(bruto: {})", previsao.suavizado, previsao.bruto);
+        thread::sleep(intervalo);
+    }
+}
+
+/*
+Treina uma arvore com características de uma janela de 500 ms sobre papete.csv e imprime o que
+ela prevê com a papete, em loop. Só usa as amostras com instante, gravadas pela coleta contínua.
+*/
+fn teste_dinamica() {
+    let intervalo = time::Duration::from_millis(50);
+
+    let dados: Vec<DadoPapete> = csv_helper::carregar_dados("papete.csv")
+        .expect("Erro ao ler papete.csv")
+        .into_iter()
+        .filter(|d| d.instante_ms.is_some())
+        .collect();
+    if dados.is_empty() {
+        println!("papete.csv não tem amostras com instante; grave com a coleta antes");
+        return;
+    }
+    let mut arvore = Arvore::calcular_com_caracteristicas(
+        &dados,
+        None,
+        &ConfigTreino::default(),
+        EspecCaracteristicas::dinamica(500),
+    )
+    .expect("Erro ao treinar arvore");
+    println!("Acurácia no treino: {:.3}", arvore.acuracia(&dados));
+    arvore
+        .salvar("arvore_dinamica.JSON")
+        .expect("Erro ao salvar arvore");
+
+    let mut papete = Papete::com_previsor(Box::new(arvore));
+    papete.ativar_modo_conexao_imediata(1);
+    print!("Procurando papetes...  ");
+    while papete.obter_conexoes().is_empty() {
+        thread::sleep(intervalo);
+    }
+    thread::sleep(time::Duration::from_secs(1));
+    println!("Encontradas!");
+    papete.iniciar_sessao(0);
+
+    loop {
+        let previsao = papete.obter_previsao();
+        println!("{}\t(bruto: {})", previsao.suavizado, previsao.bruto);
         thread::sleep(intervalo);
     }
 }
@@ -158,8 +404,14 @@ fn teste_arvore() {
 /*
 Faz avaliação da arvore, com os dados de papete.csv
 */
-fn aval_arvore() {
-    avaliacao::teste_10_pastas::<Arvore>();
+fn aval_arvore(validacao: &ConfigValidacao, treino: &ConfigTreino, relatorio: Option<&str>) {
+    let dados = csv_helper::carregar_dados("papete.csv").unwrap();
+    let resultado = avaliacao::validacao_cruzada::<Arvore>(&dados, validacao, treino);
+    println!("{}", resultado);
+    if let Some(prefixo) = relatorio {
+        let metadados = metadados("arvore", validacao, treino);
+        exportar(&resultado, exportacao::salvar_html, &metadados, prefixo);
+    }
 }
 
 /*
@@ -168,8 +420,20 @@ Conecta com a papete e imprime o que a rede neural esta prevendo, em loop.
 fn teste_neural() {
     let mut n = Neural::carregar("papete.pt").unwrap();
 
-    let dados = coleta(1, 2);
-    n.transferir(&dados);
+    let dados = aumento::aumentar(&coleta(1, 2), &ConfigAumento::default());
+    let populacao = csv_helper::carregar_dados("papete.csv").unwrap();
+    let resultado = n.transferir_com(
+        &dados,
+        &populacao,
+        &ConfigTransferencia {
+            qtd_replay: dados.len(),
+            ..Default::default()
+        },
+    );
+    println!(
+        "acurácia na calibração: {} -> {}",
+        resultado.acuracia_antes, resultado.acuracia_depois
+    );
 
     let intervalo = time::Duration::from_millis(50);
 
@@ -192,8 +456,123 @@ fn teste_neural() {
 /*
 Faz avaliação da rede neural, com os dados de papete.csv
 */
-fn aval_neural() {
-    avaliacao::teste_10_pastas::<Neural>();
+fn aval_neural(validacao: &ConfigValidacao, treino: &ConfigTreino, relatorio: Option<&str>) {
+    let dados = csv_helper::carregar_dados("papete.csv").unwrap();
+    let resultado = avaliacao::validacao_cruzada::<Neural>(&dados, validacao, treino);
+    println!("{}", resultado);
+    if let Some(prefixo) = relatorio {
+        let metadados = metadados("neural", validacao, treino);
+        exportar(&resultado, exportacao::salvar_html, &metadados, prefixo);
+    }
+}
+
+fn metadados(modelo: &str, validacao: &ConfigValidacao, treino: &ConfigTreino) -> Metadados {
+    Metadados::new(
+        modelo,
+        "papete.csv",
+        format!("{:?}", validacao),
+        format!("{:?}", treino),
+    )
+}
+
+/*
+Salva <prefixo>.json e <prefixo>.html com o resultado da avaliação
+*/
+fn exportar<T: Serialize>(
+    resultado: &T,
+    salvar_html: fn(&str, &T, &Metadados) -> std::io::Result<()>,
+    metadados: &Metadados,
+    prefixo: &str,
+) {
+    let json = format!("{}.json", prefixo);
+    let html = format!("{}.html", prefixo);
+    exportacao::salvar_json(&json, resultado, metadados).expect("Erro ao salvar JSON");
+    salvar_html(&html, resultado, metadados).expect("Erro ao salvar HTML");
+    println!("Relatório salvo em {} e {}", json, html);
+}
+
+/*
+Avalia todos os modelos registrados nas mesmas pastas e compara
+*/
+fn aval_todos(validacao: &ConfigValidacao, treino: &ConfigTreino, relatorio: Option<&str>) {
+    let dados = csv_helper::carregar_dados("papete.csv").unwrap();
+    let previsores = comparacao::previsores_registrados();
+    match comparacao::comparar(&dados, &previsores, validacao, treino) {
+        Ok(c) => {
+            println!("{}", c);
+            if let Some(prefixo) = relatorio {
+                let nomes: Vec<&str> = previsores.iter().map(|(nome, _)| *nome).collect();
+                let metadados = metadados(&nomes.join(", "), validacao, treino);
+                exportar(&c, exportacao::salvar_html_comparacao, &metadados, prefixo);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/*
+Avalia a calibração da rede neural: curva de acurácia em função de quantas amostras
+de cada movimento são usadas na transferência
+*/
+fn aval_transf(treino: &ConfigTreino, qtd_replay: usize, relatorio: Option<&str>) {
+    let dados = csv_helper::carregar_dados("papete.csv").unwrap();
+    let transferencia = ConfigTransferencia {
+        qtd_replay,
+        ..Default::default()
+    };
+    match avaliacao::avaliar_transferencia::<Neural>(
+        &dados,
+        &[1, 2, 5, 10, 20],
+        treino,
+        &transferencia,
+    ) {
+        Ok(curva) => {
+            println!("{:#}", curva);
+            if let Some(prefixo) = relatorio {
+                let metadados = Metadados::new(
+                    "neural",
+                    "papete.csv",
+                    "por sessão, N primeiras amostras de cada movimento".to_string(),
+                    format!("{:?} {:?}", treino, transferencia),
+                );
+                exportar(&curva, exportacao::salvar_html_curva, &metadados, prefixo);
+            }
+        }
+        Err(e) => println!("{}", e),
+    }
+}
+
+/*
+Interpreta as opções de avaliação, que vêm depois do nome do modelo
+*/
+fn opcoes_aval(args: &[String]) -> (ConfigValidacao, ConfigTreino, Option<String>) {
+    let mut validacao = ConfigValidacao::default();
+    let mut treino = ConfigTreino::default();
+    let mut relatorio = None;
+    for arg in args {
+        if arg == "balanceado" {
+            treino.pesos_classes = PesoClasses::Balanceado;
+        } else if arg == "reamostrado" {
+            treino.reamostrar = true;
+        } else if arg == "sessao" {
+            //sem pastas=N, cada sessão é uma pasta
+            validacao.estrategia = Estrategia::Sessao;
+            validacao.pastas = None;
+        } else if let Some(n) = arg.strip_prefix("pastas=") {
+            validacao.pastas = n.parse().ok();
+        } else if let Some(prefixo) = arg.strip_prefix("relatorio=") {
+            relatorio = Some(prefixo.to_string());
+        } else if arg.starts_with("replay=") {
+            //lida por aval_transf
+        } else if let Some(n) = arg.strip_prefix("seed=") {
+            let seed = n.parse().unwrap_or(0);
+            validacao.seed = seed;
+            treino.seed = seed;
+        } else {
+            println!("opção ignorada: {}", arg);
+        }
+    }
+    (validacao, treino, relatorio)
 }
 
 /*
@@ -201,13 +580,74 @@ Faz uma coleta curta, a calibração do classificador e avaliação da rede neur
 com os dados de papete.csv.
 */
 fn teste_transf() {
+    let intervalo = time::Duration::from_millis(200);
+
     println!("Primeiro precisamos de uma coleta");
     let dados = coleta(1, 1);
     let mut papete = Papete::com_previsor(Box::new(Neural::carregar("papete.pt").unwrap()));
     papete.ativar_modo_conexao_imediata(1);
-    papete.transferir(&dados);
+    papete.registrados = dados;
+    papete.iniciar_retreino();
+    while papete.estado_retreino() == EstadoRetreino::Treinando {
+        println!(
+            "{} (retreino {:.0}%)",
+            papete.obter_movimento(),
+            papete.progresso_retreino() * 100.0
+        );
+        thread::sleep(intervalo);
+    }
+    if let Some(resultado) = papete.resultado_retreino() {
+        if !resultado.melhorou() {
+            println!("Atenção: a transferência piorou a acurácia na calibração");
+        }
+    }
+    papete.manter_retreino();
+    loop {
+        println!("{}", papete.obter_movimento());
+    }
+}
+
+/*
+Faz a calibração guiada, como um jogo faria, e depois o retreino com as amostras dela.
+O resultado fica em perfil.json; se ele já existe, a calibração é pulada.
+*/
+fn teste_calibracao() {
+    let intervalo = time::Duration::from_millis(50);
+    let mut papete = Papete::com_previsor(Box::new(Neural::carregar("papete.pt").unwrap()));
+    if papete.carregar_perfil("perfil.json").is_ok() {
+        println!("Perfil carregado");
+        loop {
+            println!("{}", papete.obter_movimento());
+            thread::sleep(intervalo);
+        }
+    }
+    papete.iniciar_calibracao(ConfigCalibracao::default());
+    let mut instrucao = String::new();
+    while papete.atualizar_calibracao() != EtapaCalibracao::Concluida {
+        let calibracao = papete.calibracao().unwrap();
+        if calibracao.instrucao() != instrucao {
+            instrucao = calibracao.instrucao();
+            println!("{:?}: {}", calibracao.movimento_atual(), instrucao);
+        }
+        print!("\r{:.0}%", calibracao.progresso() * 100.0);
+        io::stdout().flush().unwrap();
+        thread::sleep(intervalo);
+    }
+    println!();
+    for movimento in papete.calibracao().unwrap().abaixo_minimo() {
+        println!("Atenção: {} abaixo da amplitude mínima", movimento);
+    }
+    papete.iniciar_retreino();
+    while papete.estado_retreino() == EstadoRetreino::Treinando {
+        thread::sleep(intervalo);
+    }
+    papete.manter_retreino();
+    if let Err(e) = papete.salvar_perfil("teste", "perfil.json") {
+        println!("Erro ao salvar perfil: {}", e);
+    }
     loop {
         println!("{}", papete.obter_movimento());
+        thread::sleep(intervalo);
     }
 }
 
@@ -229,22 +669,50 @@ fn main() {
             let outro_arg = args.get(2).map(String::as_str).unwrap_or("arvore");
             if outro_arg == "arvore" {
                 teste_arvore();
+            } else if outro_arg.starts_with("dinam") {
+                teste_dinamica();
             } else if outro_arg == "neural" {
                 teste_neural();
             } else if outro_arg.starts_with("trans") {
                 teste_transf();
+            } else if outro_arg.starts_with("calib") {
+                teste_calibracao();
+            } else if outro_arg.starts_with("bilat") {
+                teste_bilateral();
+            } else if outro_arg.starts_with("exerc") {
+                teste_exercicio();
+            } else if outro_arg.starts_with("contr") {
+                teste_controle();
+            } else if outro_arg.starts_with("orient") {
+                teste_orientacao();
             } else {
                 println!("argumento não reconhecido");
             }
         } else if args[1].starts_with("aval") {
             let outro_arg = args.get(2).map(String::as_str).unwrap_or("arvore");
+            let (validacao, treino, relatorio) = opcoes_aval(args.get(3..).unwrap_or(&[]));
             if outro_arg == "arvore" {
-                aval_arvore();
+                aval_arvore(&validacao, &treino, relatorio.as_deref());
             } else if outro_arg == "neural" {
-                aval_neural();
+                aval_neural(&validacao, &treino, relatorio.as_deref());
+            } else if outro_arg == "todos" {
+                aval_todos(&validacao, &treino, relatorio.as_deref());
+            } else if outro_arg.starts_with("trans") {
+                let replay = args
+                    .iter()
+                    .find_map(|a| a.strip_prefix("replay="))
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(0);
+                aval_transf(&treino, replay, relatorio.as_deref());
             } else {
                 println!("argumento não reconhecido");
             }
+        } else if args[1] == "arco" {
+            let perfil = args.get(2).map(String::as_str).unwrap_or("perfil.json");
+            let prefixo = args.get(3).map(String::as_str).unwrap_or("arco");
+            if let Err(e) = exportacao::exportar_arco(perfil, prefixo) {
+                println!("Erro ao exportar arco de movimento: {}", e);
+            }
         } else {
             println!("argumento não reconhecido ({})",args[1]);
         }
@@ -1,7 +1,8 @@
+use serde::{Deserialize, Serialize};
 use std::{fmt, str::FromStr};
 
 #[repr(C)]
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Movimento {
     Dorsiflexao,
     Flexao,
@@ -72,6 +73,17 @@ impl Movimento {
         }
     }
 
+    /*
+    Movimento equivalente no outro pé: Eversão e Inversão trocam, os demais se mantêm
+    */
+    pub fn espelhado(&self) -> Movimento {
+        match self {
+            Self::Eversao => Self::Inversao,
+            Self::Inversao => Self::Eversao,
+            outro => *outro,
+        }
+    }
+
     pub fn todos() -> [Movimento; 5] {
         [
             Self::Dorsiflexao,
@@ -1,7 +1,9 @@
+use crate::balanceamento::{self, ConfigTreino};
 use crate::dado_papete::DadoPapete;
 use crate::movimento::Movimento;
-use crate::previsor::Previsor;
+use crate::previsor::{ControleTreino, Previsor, ResultadoTransferencia};
 
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 use tch::{
     nn::{self, Module, OptimizerConfig, VarStore},
     Tensor,
@@ -11,6 +13,39 @@ use tch::{
 pub struct Neural {
     vs: VarStore,
     layers: [nn::Linear; 4],
+    //usados por transferir; a população é de onde saem as amostras de replay
+    transferencia: ConfigTransferencia,
+    populacao: Vec<DadoPapete>,
+}
+
+/*
+Como a transferência adapta a rede aos dados de calibração de um paciente:
+- camadas_congeladas: camadas cujos parâmetros não são alterados;
+- qtd_replay: quantas amostras do dataset original são misturadas à calibração;
+- peso_regularizacao: puxa os pesos de volta aos de antes da transferência (L2-SP);
+- seed: torna a amostragem do replay reprodutível.
+*/
+#[derive(Clone, Debug)]
+pub struct ConfigTransferencia {
+    pub camadas_congeladas: [bool; 4],
+    pub qtd_replay: usize,
+    pub peso_regularizacao: f64,
+    pub iteracoes: usize,
+    pub taxa_aprendizado: f64,
+    pub seed: u64,
+}
+
+impl Default for ConfigTransferencia {
+    fn default() -> Self {
+        ConfigTransferencia {
+            camadas_congeladas: [true, true, false, false],
+            qtd_replay: 0,
+            peso_regularizacao: 1e-2,
+            iteracoes: 3000,
+            taxa_aprendizado: 1e-3,
+            seed: 0,
+        }
+    }
 }
 
 impl Neural {
@@ -24,19 +59,142 @@ impl Neural {
             nn::linear(r, 30, 5, Default::default()),
         ];
 
-        Self { vs, layers }
+        Self {
+            vs,
+            layers,
+            transferencia: ConfigTransferencia::default(),
+            populacao: Vec::new(),
+        }
     }
 
     pub fn treinar_de_dataset(&mut self, dataset: &[DadoPapete]) {
+        self.treinar_de_dataset_com(dataset, None, &ConfigTreino::default());
+    }
+
+    /*
+    O erro de cada amostra é multiplicado pelo seu peso; com pesos iguais equivale ao MSE comum
+    */
+    pub fn treinar_de_dataset_com(
+        &mut self,
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+    ) {
+        let (dados, pesos) = balanceamento::preparar(dataset, pesos_amostras, config);
+        let (entradas, saidas_esperadas) = Neural::tensores(&dados);
+        let soma_pesos: f32 = pesos.iter().sum();
+        let pesos = Tensor::of_slice(&pesos);
+
+        let mut opt = nn::Adam::default().build(&self.vs, 1e-3).unwrap();
+
+        println!("Treinando...");
+        for _ in 0..500 {
+            let loss = (self.forward(&entradas) - &saidas_esperadas)
+                .square()
+                .mean1(&[1], false, tch::Kind::Float)
+                .g_mul(&pesos)
+                .sum(tch::Kind::Float)
+                / soma_pesos as f64;
+
+            opt.backward_step(&loss);
+        }
+    }
+
+    /*
+    Adapta a rede aos dados de calibração. As amostras de replay são sorteadas de `populacao`,
+    normalmente o dataset usado no treino original.
+    */
+    pub fn transferir_com(
+        &mut self,
+        dataset: &[DadoPapete],
+        populacao: &[DadoPapete],
+        config: &ConfigTransferencia,
+    ) -> ResultadoTransferencia {
+        self.transferir_com_controle(dataset, populacao, config, &ControleTreino::default())
+            .unwrap()
+    }
+
+    pub fn transferir_com_controle(
+        &mut self,
+        dataset: &[DadoPapete],
+        populacao: &[DadoPapete],
+        config: &ConfigTransferencia,
+        controle: &ControleTreino,
+    ) -> Option<ResultadoTransferencia> {
+        let acuracia_antes = self.acuracia(dataset);
+
+        let mut dados: Vec<DadoPapete> = dataset.to_vec();
+        let mut rng = StdRng::seed_from_u64(config.seed);
+        dados.extend(
+            populacao
+                .choose_multiple(&mut rng, config.qtd_replay.min(populacao.len()))
+                .copied(),
+        );
+        let (entradas, saidas_esperadas) = Neural::tensores(&dados);
+
+        //pesos de referencia, copiados antes de qualquer alteração
+        let referencia: Vec<(Tensor, Tensor)> = self
+            .layers
+            .iter()
+            .map(|l| (l.ws.detach().copy(), l.bs.detach().copy()))
+            .collect();
+
+        self.congelar(&config.camadas_congeladas);
+        let mut opt = nn::Adam::default()
+            .build(&self.vs, config.taxa_aprendizado)
+            .unwrap();
+
+        println!("Transferindo...");
+        for i in 0..config.iteracoes {
+            if controle.cancelado() {
+                self.congelar(&[false; 4]);
+                return None;
+            }
+            controle.informar(i as f32 / config.iteracoes as f32);
+
+            let mut loss = self
+                .forward(&entradas)
+                .mse_loss(&saidas_esperadas, tch::Reduction::Mean);
+
+            if config.peso_regularizacao > 0.0 {
+                for (i, (ws, bs)) in referencia.iter().enumerate() {
+                    if !config.camadas_congeladas[i] {
+                        let distancia = (&self.layers[i].ws - ws).square().sum(tch::Kind::Float)
+                            + (&self.layers[i].bs - bs).square().sum(tch::Kind::Float);
+                        loss = loss + distancia * config.peso_regularizacao;
+                    }
+                }
+            }
+
+            opt.backward_step(&loss);
+        }
+        self.congelar(&[false; 4]);
+        controle.informar(1.0);
+
+        Some(ResultadoTransferencia {
+            acuracia_antes,
+            acuracia_depois: self.acuracia(dataset),
+        })
+    }
+
+    /*
+    Desliga o gradiente dos parâmetros das camadas marcadas, religando nas demais
+    */
+    fn congelar(&mut self, camadas: &[bool; 4]) {
+        for (layer, &congelada) in self.layers.iter().zip(camadas.iter()) {
+            let _ = layer.ws.set_requires_grad(!congelada);
+            let _ = layer.bs.set_requires_grad(!congelada);
+        }
+    }
+
+    fn tensores(dataset: &[DadoPapete]) -> (Tensor, Tensor) {
         let entradas: Vec<_> = dataset
             .iter()
-            .map(|x| Vec::from(x.array_normalizado()))
-            .flatten()
+            .flat_map(|x| Vec::from(x.array_normalizado()))
             .collect();
         let saidas_esperadas: Vec<f32> = dataset
             .iter()
-            .map(|x| Vec::from(x.movimento.unwrap().como_entrada_nn()))
-            .flatten()
+            .flat_map(|x| Vec::from(x.movimento.unwrap().como_entrada_nn()))
             .collect();
 
         let entradas = Tensor::of_slice(&entradas)
@@ -44,17 +202,7 @@ impl Neural {
             .to_kind(tch::Kind::Float);
         let saidas_esperadas =
             Tensor::of_slice(&saidas_esperadas).reshape(&[dataset.len() as i64, 5]);
-
-        let mut opt = nn::Adam::default().build(&self.vs, 1e-3).unwrap();
-
-        println!("Treinando...");
-        for _ in 0..500 {
-            let loss = self
-                .forward(&entradas)
-                .mse_loss(&saidas_esperadas, tch::Reduction::Mean);
-
-            opt.backward_step(&loss);
-        }
+        (entradas, saidas_esperadas)
     }
 
     pub fn obter_saida(&mut self, input_tensor: &[f32; 3]) -> [f32; 5] {
@@ -71,13 +219,6 @@ impl Neural {
             output.double_value(&[4]) as f32,
         ]
     }
-
-    fn custom_forward(&self, xs: &Tensor) -> Tensor {
-        let mut x = xs.apply(&self.layers[0]).sigmoid().set_requires_grad(false);
-        x = x.apply(&self.layers[1]).set_requires_grad(false).sigmoid();
-        x = x.apply(&self.layers[2]).sigmoid();
-        x.apply(&self.layers[3]).sigmoid()
-    }
 }
 
 impl Module for Neural {
@@ -93,6 +234,17 @@ impl Previsor for Neural {
     fn calcular_de_dataset(dataset: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
         let mut n = Neural::new();
         n.treinar_de_dataset(dataset);
+        n.populacao = dataset.to_vec();
+        Ok(n)
+    }
+    fn calcular_de_dataset_com(
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut n = Neural::new();
+        n.treinar_de_dataset_com(dataset, pesos_amostras, config);
+        n.populacao = dataset.to_vec();
         Ok(n)
     }
     fn carregar(endereco: &str) -> Result<Self, Box<dyn std::error::Error>> {
@@ -121,33 +273,110 @@ impl Previsor for Neural {
     fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
         entrada.iter().map(|e| self.prever(*e)).collect()
     }
-    fn transferir(&mut self, dataset: &[DadoPapete]) {
-        let entradas: Vec<_> = dataset
-            .iter()
-            .map(|x| Vec::from(x.array_normalizado()))
-            .flatten()
-            .collect();
-        let saidas_esperadas: Vec<f32> = dataset
+    fn transferir(&mut self, dataset: &[DadoPapete]) -> ResultadoTransferencia {
+        self.transferir_controlado(dataset, &ControleTreino::default())
+            .unwrap()
+    }
+    /*
+    Usa a configuração e a população dadas em configurar_transferencia; a rede treinada por
+    calcular_de_dataset já tem o próprio dataset como população
+    */
+    fn transferir_controlado(
+        &mut self,
+        dataset: &[DadoPapete],
+        controle: &ControleTreino,
+    ) -> Option<ResultadoTransferencia> {
+        let populacao = std::mem::take(&mut self.populacao);
+        let config = self.transferencia.clone();
+        let resultado = self.transferir_com_controle(dataset, &populacao, &config, controle);
+        self.populacao = populacao;
+        resultado
+    }
+    fn configurar_transferencia(&mut self, config: &ConfigTransferencia, populacao: &[DadoPapete]) {
+        self.transferencia = config.clone();
+        self.populacao = populacao.to_vec();
+    }
+    fn suporta_transferencia(&self) -> bool {
+        true
+    }
+    fn clonar(&self) -> Result<Box<dyn Previsor>, Box<dyn std::error::Error>> {
+        let mut n = Neural::new();
+        n.vs.copy(&self.vs)?;
+        n.transferencia = self.transferencia.clone();
+        n.populacao = self.populacao.clone();
+        Ok(Box::new(n))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::neural::*;
+
+    fn parametros(n: &Neural) -> Vec<Tensor> {
+        n.layers
             .iter()
-            .map(|x| Vec::from(x.movimento.unwrap().como_entrada_nn()))
-            .flatten()
-            .collect();
+            .flat_map(|l| [l.ws.detach().copy(), l.bs.detach().copy()])
+            .collect()
+    }
 
-        let entradas = Tensor::of_slice(&entradas)
-            .reshape(&[dataset.len() as i64, 3])
-            .to_kind(tch::Kind::Float);
-        let saidas_esperadas =
-            Tensor::of_slice(&saidas_esperadas).reshape(&[dataset.len() as i64, 5]);
+    fn distancia(a: &[Tensor], b: &[Tensor]) -> f64 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).square().sum(tch::Kind::Float).double_value(&[]))
+            .sum()
+    }
 
-        let mut opt = nn::Adam::default().build(&self.vs, 1e-3).unwrap();
+    #[test]
+    fn congelamento_e_regularizacao() {
+        let calibracao: Vec<DadoPapete> = (0..20)
+            .map(|i| {
+                let movimento = if i % 2 == 0 {
+                    Movimento::Dorsiflexao
+                } else {
+                    Movimento::Flexao
+                };
+                DadoPapete::completo(0.05 * i as f32, -0.3, i % 4 < 2, movimento, 0)
+            })
+            .collect();
+        let config = ConfigTransferencia {
+            iteracoes: 50,
+            taxa_aprendizado: 1e-2,
+            peso_regularizacao: 0.0,
+            ..Default::default()
+        };
+        let n = Neural::new();
+        let antes = parametros(&n);
 
-        println!("\"Transferindo\"...");
-        for _ in 0..3000 {
-            let loss = self
-                .custom_forward(&entradas)
-                .mse_loss(&saidas_esperadas, tch::Reduction::Mean);
+        //todas as camadas congeladas: nada muda
+        let mut congelada = Neural::new();
+        congelada.vs.copy(&n.vs).unwrap();
+        congelada.transferir_com(
+            &calibracao,
+            &[],
+            &ConfigTransferencia {
+                camadas_congeladas: [true; 4],
+                ..config.clone()
+            },
+        );
+        assert_eq!(distancia(&antes, &parametros(&congelada)), 0.0);
 
-            opt.backward_step(&loss);
-        }
+        //só as camadas livres mudam, e a regularização as mantém mais perto das originais
+        let mut livre = Neural::new();
+        livre.vs.copy(&n.vs).unwrap();
+        livre.transferir_com(&calibracao, &[], &config);
+        let mut regularizada = Neural::new();
+        regularizada.vs.copy(&n.vs).unwrap();
+        regularizada.transferir_com(
+            &calibracao,
+            &[],
+            &ConfigTransferencia {
+                peso_regularizacao: 10.0,
+                ..config.clone()
+            },
+        );
+        let livre = parametros(&livre);
+        assert_eq!(distancia(&antes[..4], &livre[..4]), 0.0);
+        assert!(distancia(&antes[4..], &livre[4..]) > 0.0);
+        assert!(distancia(&antes, &parametros(&regularizada)) < distancia(&antes, &livre));
     }
 }
@@ -8,74 +8,333 @@ Precisa pedir de novo para conectar
 */
 
 use crate::{
-    comm::sensor::Sensores, conexao::Conexao, csv_helper, dado_papete::DadoPapete,
-    movimento::Movimento, neural::Neural, previsor::Previsor,
+    arco::ArcoMovimento,
+    balanceamento::ConfigTreino,
+    bilateral::{self, MovimentoBilateral},
+    calibracao::{Calibracao, CapturaOffset, ConfigCalibracao, EtapaCalibracao, QualidadeOffset},
+    caracteristicas::{EspecCaracteristicas, JanelaCaracteristicas},
+    comm::{
+        fonte::FonteSensores,
+        orientacao::{ConfigOrientacao, Orientacao},
+        sensor::Sensores,
+    },
+    conexao::Conexao,
+    controle::{self, ConfigControle, Controle},
+    csv_helper,
+    dado_papete::DadoPapete,
+    dificuldade::{DicasDificuldade, Dificuldade},
+    eventos::{DetectorEventos, EventoMovimento},
+    exercicio::{ConfigExercicio, Exercicio, ProgressoExercicio},
+    fadiga::{AlertasFadiga, AnaliseFadiga, ConfigFadiga},
+    lado::{Lado, PorLado},
+    movimento::Movimento,
+    neural::{ConfigTransferencia, Neural},
+    perfil::{Perfil, RegistroSessao},
+    previsor::{ControleTreino, Previsor, ResultadoTransferencia},
+    suavizacao::{ConfigSuavizacao, Previsao, Suavizador},
 };
 
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+//eventos guardados até alguém drená-los; além disso, os mais antigos são descartados
+static MAX_EVENTOS: usize = 256;
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EstadoRetreino {
+    Ocioso,
+    Treinando,
+    Concluido,
+    Cancelado,
+    Falhou,
+    //terminou com acurácia igual ou melhor; o jogo decide com manter_retreino ou descartar_retreino
+    AguardandoConfirmacao,
+    //terminou pior que o previsor atual, que foi mantido
+    Descartado,
+}
+
+/*
+Retreino rodando em segundo plano sobre uma cópia do previsor.
+Quando a thread termina, a cópia adaptada fica esperando o jogo confirmar a troca.
+*/
+struct Retreino {
+    controle: ControleTreino,
+    thread: JoinHandle<Option<(Box<dyn Previsor>, ResultadoTransferencia)>>,
+}
+
+/*
+Estado de cada pé: offset de repouso, a captura dele, as amplitudes da última calibração
+a suavização das previsões, a detecção de eventos e o arco de movimento da sessão
+*/
+#[derive(Clone, Debug, Default)]
+pub struct EstadoPe {
+    pub offset: Option<DadoPapete>,
+    captura: CapturaOffset,
+    //amplitude máxima (rad) em cada movimento, indexada por Movimento::as_usize
+    pub amplitudes: [f32; 5],
+    suavizador: Suavizador,
+    //última previsão do pé, bruta e suavizada
+    pub previsao: Option<Previsao>,
+    eventos: DetectorEventos,
+    pub arco: ArcoMovimento,
+    //janela das características pedidas pelo previsor
+    janela: JanelaCaracteristicas,
+}
 
 pub struct Papete {
-    offsets: (Option<DadoPapete>, Option<DadoPapete>),
+    pes: PorLado<EstadoPe>,
     previsor: Option<Box<dyn Previsor>>,
     pub registrados: Vec<DadoPapete>,
     sessao: Option<u32>,
-    sensores: Sensores,
+    sensores: Box<dyn FonteSensores>,
+    retreino: Option<Retreino>,
+    estado_retreino: EstadoRetreino,
+    resultado_retreino: Option<ResultadoTransferencia>,
+    //previsor adaptado do último retreino, até o jogo mantê-lo ou descartá-lo
+    candidato: Option<Box<dyn Previsor>>,
+    calibracao: Option<Calibracao>,
+    //perfil carregado ou salvo por último, para manter o histórico
+    perfil: Option<Perfil>,
+    //o previsor atual veio de um retreino ou de um perfil
+    modelo_adaptado: bool,
+    eventos: VecDeque<EventoMovimento>,
+    assinantes: Vec<Sender<EventoMovimento>>,
+    exercicio: Option<Exercicio>,
+    controle: ConfigControle,
+    dificuldade: Dificuldade,
+    fadiga: AnaliseFadiga,
+    //configuração e população de replay dos próximos retreinos, se o jogo definiu
+    transferencia: Option<(ConfigTransferencia, Vec<DadoPapete>)>,
 }
 
 impl Papete {
     pub fn new() -> Papete {
-        Papete {
-            offsets: (None, None),
-            previsor: None,
-            registrados: Vec::new(),
-            sessao: None,
-            sensores: Sensores::new(),
-        }
+        Papete::com_fonte(None, Box::new(Sensores::new()))
     }
 
     pub fn com_previsor(previsor: Box<dyn Previsor>) -> Papete {
+        Papete::com_fonte(Some(previsor), Box::new(Sensores::new()))
+    }
+
+    /*
+    Papete lendo de outra fonte, sem as threads de comunicação de Sensores
+    */
+    pub fn com_fonte(
+        previsor: Option<Box<dyn Previsor>>,
+        sensores: Box<dyn FonteSensores>,
+    ) -> Papete {
         Papete {
-            offsets: (None, None),
-            previsor: Some(previsor),
+            pes: PorLado::default(),
+            previsor,
             registrados: Vec::new(),
             sessao: None,
-            sensores: Sensores::new(),
+            sensores,
+            retreino: None,
+            estado_retreino: EstadoRetreino::Ocioso,
+            resultado_retreino: None,
+            candidato: None,
+            calibracao: None,
+            perfil: None,
+            modelo_adaptado: false,
+            eventos: VecDeque::new(),
+            assinantes: Vec::new(),
+            exercicio: None,
+            controle: ConfigControle::default(),
+            dificuldade: Dificuldade::default(),
+            fadiga: AnaliseFadiga::default(),
+            transferencia: None,
         }
     }
 
+    /*
+    Movimento suavizado do primeiro pé (o esquerdo, se houver) que já tem offset
+    */
     pub fn obter_movimento(&mut self) -> Movimento {
-        let nomes_sensores = self.sensores.obter_sensores_ativos();
-        let mut buffer: Vec<Vec<f32>> = Vec::with_capacity(2);
-        self.sensores.obter_valores(&mut buffer);
-        for i in 0..buffer.len() {
-            if let Some(pitch) = buffer[i].get(0) {
-                if let Some(roll) = buffer[i].get(1) {
-                    let pe_esq = nomes_sensores[i] == "papE";
-                    let mut dado = DadoPapete::basico(pitch.clone(), roll.clone(), pe_esq);
-                    
-                    if pe_esq{
-                        if let Some(offset) = self.offsets.0 {
-                            dado -= offset;
-                            return self.previsor.as_mut().unwrap().prever(dado);
-                        } else {
-                            self.offsets.0 = Some(dado);
-                            println!("Coloquei offset 0");
-                        }
-                    }
-                    else {
-                        if let Some(offset) = self.offsets.1 {
-                            dado -= offset;
-                            return self.previsor.as_mut().unwrap().prever(dado);
-                        } else {
-                            self.offsets.1 = Some(dado);
-                            println!("Coloquei offset 1");
-                        }
-                    }
-                }
+        self.obter_previsao().suavizado
+    }
+
+    /*
+    Previsão bruta e suavizada do primeiro pé que já tem offset.
+    Todos os pés conectados são classificados, para que eventos, exercício, arco e fadiga
+    acompanhem os dois; só a previsão retornada é a de um deles.
+    */
+    pub fn obter_previsao(&mut self) -> Previsao {
+        self.verificar_retreino();
+        let dados = self.obter_dados();
+        let mut escolhida = None;
+        for lado in Lado::todos() {
+            if let Some(dado) = dados[lado].and_then(|d| self.corrigir(d, true)) {
+                let previsao = self.prever(dado);
+                escolhida.get_or_insert(previsao);
+            }
+        }
+        escolhida.unwrap_or_else(|| {
+            println!("Não consegui papete");
+            Previsao {
+                bruto: Movimento::Repouso,
+                suavizado: Movimento::Repouso,
+            }
+        })
+    }
+
+    /*
+    Prevê cada pé separadamente e combina os dois num gesto
+    */
+    pub fn obter_movimento_bilateral(&mut self) -> MovimentoBilateral {
+        self.verificar_retreino();
+        let dados = self.obter_dados();
+        let mut previstos = PorLado::default();
+        for lado in Lado::todos() {
+            if let Some(dado) = dados[lado].and_then(|d| self.corrigir(d, true)) {
+                previstos[lado] = Some(self.prever(dado).suavizado);
+            }
+        }
+        bilateral::combinar(previstos.esq, previstos.dir)
+    }
+
+    /*
+    Classifica o dado corrigido, passa pelo suavizador do pé dele e publica os eventos
+    */
+    fn prever(&mut self, dado: DadoPapete) -> Previsao {
+        let agora = Instant::now();
+        let previsor = self.previsor.as_mut().unwrap();
+        let espec = previsor.caracteristicas();
+        let pe = &mut self.pes[dado.lado()];
+        if pe.janela.espec() != &espec {
+            pe.janela = JanelaCaracteristicas::new(espec);
+        }
+        let caracteristicas = pe.janela.atualizar(&dado, agora);
+        let bruto = previsor.prever_caracteristicas(&caracteristicas, dado);
+        let previsao = pe.suavizador.atualizar(bruto, agora);
+        pe.previsao = Some(previsao);
+        pe.arco.atualizar(previsao.suavizado, &dado);
+        let amplitudes = pe.amplitudes;
+        let eventos = pe.eventos.atualizar(previsao.suavizado, &dado, agora);
+        //antes da dificuldade, que usa os alertas com a tentativa que acabou de terminar
+        self.fadiga.atualizar(previsao, &dado, agora);
+        for evento in eventos {
+            self.dificuldade.registrar(
+                &evento,
+                amplitudes[evento.movimento.as_usize()],
+                self.fadiga.alertas(),
+            );
+            self.assinantes.retain(|a| a.send(evento).is_ok());
+            if self.eventos.len() >= MAX_EVENTOS {
+                self.eventos.pop_front();
             }
+            self.eventos.push_back(evento);
         }
-        println!("Não consegui papete");
-        Movimento::Repouso
+        if let Some(exercicio) = &mut self.exercicio {
+            exercicio.atualizar(previsao.suavizado, &dado, agora);
+        }
+        previsao
+    }
+
+    /*
+    Retira até max eventos, do mais antigo ao mais novo. Os eventos só são detectados
+    nas chamadas de obter_movimento, obter_previsao e obter_movimento_bilateral.
+    */
+    pub fn drenar_eventos(&mut self, max: usize) -> impl Iterator<Item = EventoMovimento> + '_ {
+        let n = max.min(self.eventos.len());
+        self.eventos.drain(..n)
+    }
+
+    /*
+    Canal que recebe uma cópia de cada evento a partir de agora, independente de drenar_eventos
+    */
+    #[allow(dead_code)]
+    pub fn assinar_eventos(&mut self) -> Receiver<EventoMovimento> {
+        let (tx, rx) = mpsc::channel();
+        self.assinantes.push(tx);
+        rx
+    }
+
+    /*
+    Última previsão do pé, para registrar o fluxo bruto junto do suavizado
+    */
+    #[allow(dead_code)]
+    pub fn previsao(&self, lado: Lado) -> Option<Previsao> {
+        self.pes[lado].previsao
+    }
+
+    /*
+    Inclinação do pé em cada eixo, de -1 a 1, relativa à amplitude calibrada dele.
+    None se o pé não está conectado ou ainda não tem offset.
+    */
+    pub fn obter_controle(&mut self, lado: Lado) -> Option<Controle> {
+        let dado = self.obter_dados()[lado]?;
+        let dado = self.corrigir(dado, true)?;
+        Some(controle::calcular(
+            &dado,
+            &self.pes[lado].amplitudes,
+            &self.controle,
+        ))
+    }
+
+    pub fn configurar_controle(&mut self, config: ConfigControle) {
+        self.controle = config;
+    }
+
+    /*
+    Parâmetros de dificuldade sugeridos para o próximo estímulo, com o desempenho da sessão
+    */
+    pub fn dicas_dificuldade(&self) -> DicasDificuldade {
+        self.dificuldade.dicas()
+    }
+
+    /*
+    Alertas de fadiga e compensação das últimas tentativas da sessão
+    */
+    pub fn alertas_fadiga(&self) -> AlertasFadiga {
+        self.fadiga.alertas()
+    }
+
+    #[allow(dead_code)]
+    pub fn configurar_fadiga(&mut self, config: ConfigFadiga) {
+        self.fadiga.configurar(config);
+    }
+
+    /*
+    Maiores ângulos (graus) do pé em cada direção desde o início da sessão
+    */
+    pub fn arco_movimento(&self, lado: Lado) -> ArcoMovimento {
+        self.pes[lado].arco
+    }
+
+    /*
+    Troca a suavização dos dois pés, recomeçando do repouso
+    */
+    #[allow(dead_code)]
+    pub fn configurar_suavizacao(&mut self, config: ConfigSuavizacao) {
+        for (_, pe) in self.pes.iter_mut() {
+            pe.suavizador = Suavizador::new(config.clone());
+            pe.previsao = None;
+        }
+    }
+
+    /*
+    Pitch, roll e yaw do filtro de orientação do pé, sem descontar o repouso.
+    None se o pé não está conectado ou o firmware dele não manda o giroscópio.
+    */
+    pub fn orientacao(&self, lado: Lado) -> Option<Orientacao> {
+        self.sensores
+            .obter_orientacoes()
+            .into_iter()
+            .find(|(dispositivo, _)| Lado::de_dispositivo(dispositivo) == Some(lado))
+            .map(|(_, orientacao)| orientacao)
+    }
+
+    /*
+    Troca o filtro de orientação dos sensores; como pitch e roll mudam, vale recalibrar o repouso
+    */
+    pub fn configurar_orientacao(&mut self, config: ConfigOrientacao) {
+        self.sensores.configurar_orientacao(config);
     }
 
     pub fn obter_conexoes(&self) -> Vec<Conexao> {
@@ -86,44 +345,45 @@ impl Papete {
             .collect()
     }
 
-    pub fn obter_dados(&self) -> (Option<DadoPapete>, Option<DadoPapete>) {
-        let mut dados = (None, None);
-
-        let nomes_sensores = self.sensores.obter_sensores_ativos();
-        let mut buffer: Vec<Vec<f32>> = Vec::with_capacity(2);
-        self.sensores.obter_valores(&mut buffer);
-        for i in 0..buffer.len() {
-            if let Some(pitch) = buffer[i].get(0) {
-                if let Some(roll) = buffer[i].get(1) {
-                    let pe_esq = nomes_sensores[i] == "papE";
-                    let dado = DadoPapete::basico(pitch.clone(), roll.clone(), pe_esq);
-                    if pe_esq {
-                        //possivel ponto de erro! Não sei se é 1 ou 0
-                        dados.1 = Some(dado);
-                    } else {
-                        dados.0 = Some(dado);
-                    }
+    /*
+    Leitura atual de cada papete, sem offset
+    */
+    pub fn obter_dados(&self) -> PorLado<Option<DadoPapete>> {
+        let mut dados = PorLado::default();
+        for (dispositivo, valores) in self.sensores.obter_leituras() {
+            if let Some(lado) = Lado::de_dispositivo(&dispositivo) {
+                if let Some(dado) = DadoPapete::de_valores(&valores, lado.esq()) {
+                    dados[lado] = Some(dado);
                 }
             }
         }
-        return dados;
+        dados
     }
 
     #[allow(dead_code)]
     pub fn obter_dados_qqr(&self) -> Option<DadoPapete> {
-        let nomes_sensores = self.sensores.obter_sensores_ativos();
-        let mut buffer: Vec<Vec<f32>> = Vec::with_capacity(2);
-        self.sensores.obter_valores(&mut buffer);
-        for i in 0..buffer.len() {
-            if let Some(pitch) = buffer[i].get(0) {
-                if let Some(roll) = buffer[i].get(1) {
-                    let pe_esq = nomes_sensores[i] == "papE";
-                    let dado = DadoPapete::basico(pitch.clone(), roll.clone(), pe_esq);
-                    return Some(dado);
+        let dados = self.obter_dados();
+        dados.esq.or(dados.dir)
+    }
+
+    /*
+    Desconta o offset do pé do dado. Se o pé ainda não tem offset, não há dado corrigido
+    e, se capturar, a leitura alimenta a captura de repouso.
+    */
+    fn corrigir(&mut self, mut dado: DadoPapete, capturar: bool) -> Option<DadoPapete> {
+        let pe = &mut self.pes[dado.lado()];
+        match pe.offset {
+            Some(offset) => {
+                dado -= offset;
+                Some(dado)
+            }
+            None => {
+                if capturar {
+                    pe.offset = pe.captura.adicionar(dado);
                 }
+                None
             }
         }
-        None
     }
 
     #[allow(dead_code)]
@@ -138,7 +398,13 @@ impl Papete {
 
     pub fn iniciar_sessao(&mut self, qtd_esperada: usize) {
         self.registrados = Vec::with_capacity(qtd_esperada);
-        self.offsets = self.obter_dados();
+        self.recalibrar_repouso();
+        for (_, pe) in self.pes.iter_mut() {
+            pe.arco = ArcoMovimento::default();
+            pe.janela.limpar();
+        }
+        self.dificuldade.nova_sessao();
+        self.fadiga.nova_sessao();
         self.sessao = Some(
             SystemTime::now()
                 .duration_since(UNIX_EPOCH)
@@ -148,29 +414,40 @@ impl Papete {
     }
     pub fn registrar(&mut self, movimento: Movimento) -> bool {
         let dados = self.obter_dados();
+        let instante = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
         let mut res = false;
-        for lado in [
-            (dados.0, &mut self.offsets.0),
-            (dados.1, &mut self.offsets.1),
-        ] {
-            if let Some(mut x) = lado.0 {
-                if let Some(offset) = lado.1 {
-                    x.movimento = Some(movimento);
-                    x.sessao = Some(if let Some(sessao) = self.sessao {
-                        sessao
-                    } else {
-                        0
-                    });
-                    x -= *offset;
-                    self.registrados.push(x);
-                    res = true;
-                } else if movimento == Movimento::Repouso {
-                    *lado.1 = lado.0.clone();
-                }
+        for lado in Lado::todos() {
+            //o offset só é capturado com os pés em repouso
+            let capturar = movimento == Movimento::Repouso;
+            if let Some(mut x) = dados[lado].and_then(|d| self.corrigir(d, capturar)) {
+                x.movimento = Some(movimento);
+                x.sessao = Some(self.sessao.unwrap_or(0));
+                x.instante_ms = Some(instante);
+                self.registrados.push(x);
+                res = true;
             }
         }
         res
     }
+
+    /*
+    Descarta os offsets e volta a capturá-los com as próximas leituras (em obter_movimento,
+    ou em registrar com Repouso). Cada pé só recebe offset quando uma janela inteira fica estável.
+    */
+    pub fn recalibrar_repouso(&mut self) {
+        for (_, pe) in self.pes.iter_mut() {
+            pe.offset = None;
+            pe.captura.reiniciar();
+        }
+    }
+
+    pub fn qualidade_offset(&self, lado: Lado) -> QualidadeOffset {
+        self.pes[lado].captura.qualidade()
+    }
+
     #[allow(dead_code)]
     pub fn deregistrar(&mut self) {
         self.registrados.pop();
@@ -180,6 +457,278 @@ impl Papete {
         csv_helper::salvar_dados(destino, &self.registrados)
     }
 
+    /*
+    Começa uma calibração guiada, descartando a anterior.
+    O jogo chama atualizar_calibracao a cada quadro e mostra a instrução da calibração.
+    */
+    pub fn iniciar_calibracao(&mut self, config: ConfigCalibracao) {
+        self.calibracao = Some(Calibracao::new(config));
+    }
+
+    /*
+    Passa as leituras atuais para a calibração. Quando ela termina, os offsets e as amostras
+    registradas passam a ser os da calibração, numa sessão nova.
+    */
+    pub fn atualizar_calibracao(&mut self) -> EtapaCalibracao {
+        let dados = self.obter_dados();
+        let calibracao = match self.calibracao.as_mut() {
+            Some(calibracao) => calibracao,
+            None => return EtapaCalibracao::Inativa,
+        };
+        let anterior = calibracao.etapa();
+        let etapa = calibracao.atualizar(&dados, Instant::now());
+
+        if etapa == EtapaCalibracao::Concluida && anterior != EtapaCalibracao::Concluida {
+            let sessao = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis() as u32;
+            for (lado, pe) in self.pes.iter_mut() {
+                pe.offset = calibracao.offset(lado);
+                pe.amplitudes = calibracao.amplitudes_pe(lado);
+            }
+            self.registrados = calibracao
+                .amostras()
+                .iter()
+                .map(|d| DadoPapete {
+                    sessao: Some(sessao),
+                    ..*d
+                })
+                .collect();
+            self.sessao = Some(sessao);
+        }
+        etapa
+    }
+
+    pub fn calibracao(&self) -> Option<&Calibracao> {
+        self.calibracao.as_ref()
+    }
+
+    #[allow(dead_code)]
+    pub fn cancelar_calibracao(&mut self) {
+        if let Some(calibracao) = self.calibracao.as_mut() {
+            calibracao.cancelar();
+        }
+    }
+
+    /*
+    Começa a contar as repetições do exercício, descartando o anterior.
+    As amostras vêm das chamadas de obter_movimento, obter_previsao e obter_movimento_bilateral.
+    */
+    pub fn iniciar_exercicio(&mut self, config: ConfigExercicio) {
+        self.exercicio = Some(Exercicio::new(config));
+    }
+
+    pub fn exercicio(&self) -> Option<&Exercicio> {
+        self.exercicio.as_ref()
+    }
+
+    pub fn progresso_exercicio(&self) -> Option<ProgressoExercicio> {
+        self.exercicio.as_ref().map(|e| e.progresso())
+    }
+
+    /*
+    Para de contar e devolve o exercício com as repetições feitas
+    */
+    pub fn encerrar_exercicio(&mut self) -> Option<Exercicio> {
+        self.exercicio.take()
+    }
+
+    /*
+    Começa a transferência com os dados registrados numa thread separada.
+    Enquanto ela roda, obter_movimento continua usando o previsor atual.
+    Retorna false se já há um retreino, se não há dados ou se o previsor não pode ser copiado.
+    */
+    pub fn iniciar_retreino(&mut self) -> bool {
+        self.verificar_retreino();
+        if self.retreino.is_some() || self.registrados.is_empty() {
+            return false;
+        }
+        let mut copia = match self.previsor.as_ref().map(|p| p.clonar()) {
+            Some(Ok(copia)) => copia,
+            _ => return false,
+        };
+        if let Some((config, populacao)) = &self.transferencia {
+            copia.configurar_transferencia(config, populacao);
+        }
+        let dados = self.registrados.clone();
+        let controle = ControleTreino::default();
+        let controle_thread = controle.clone();
+        let thread = thread::spawn(move || {
+            copia
+                .transferir_controlado(&dados, &controle_thread)
+                .map(|resultado| (copia, resultado))
+        });
+        self.retreino = Some(Retreino { controle, thread });
+        self.estado_retreino = EstadoRetreino::Treinando;
+        self.resultado_retreino = None;
+        self.candidato = None;
+        true
+    }
+
+    pub fn cancelar_retreino(&mut self) {
+        if let Some(retreino) = &self.retreino {
+            retreino.controle.cancelar();
+        }
+    }
+
+    /*
+    Troca o previsor pelo adaptado do último retreino.
+    Retorna false se não há um esperando confirmação.
+    */
+    pub fn manter_retreino(&mut self) -> bool {
+        self.verificar_retreino();
+        let previsor = match self.candidato.take() {
+            Some(previsor) => previsor,
+            None => return false,
+        };
+        self.previsor = Some(previsor);
+        self.modelo_adaptado = true;
+        //o que o previsor aprendeu vira a referência de fadiga e compensação
+        self.fadiga.definir_linha_base(&self.registrados);
+        self.estado_retreino = EstadoRetreino::Concluido;
+        true
+    }
+
+    /*
+    Continua com o previsor atual e joga fora o adaptado
+    */
+    #[allow(dead_code)]
+    pub fn descartar_retreino(&mut self) {
+        self.verificar_retreino();
+        if self.candidato.take().is_some() {
+            self.estado_retreino = EstadoRetreino::Descartado;
+        }
+    }
+
+    pub fn progresso_retreino(&mut self) -> f32 {
+        self.verificar_retreino();
+        match &self.retreino {
+            Some(retreino) => retreino.controle.progresso(),
+            None if matches!(
+                self.estado_retreino,
+                EstadoRetreino::Concluido
+                    | EstadoRetreino::AguardandoConfirmacao
+                    | EstadoRetreino::Descartado
+            ) =>
+            {
+                1.0
+            }
+            None => 0.0,
+        }
+    }
+
+    pub fn estado_retreino(&mut self) -> EstadoRetreino {
+        self.verificar_retreino();
+        self.estado_retreino
+    }
+
+    pub fn resultado_retreino(&mut self) -> Option<ResultadoTransferencia> {
+        self.verificar_retreino();
+        self.resultado_retreino
+    }
+
+    /*
+    Se a thread de retreino terminou, guarda o previsor adaptado para confirmação, a menos que
+    ele tenha piorado na calibração
+    */
+    fn verificar_retreino(&mut self) {
+        if !self
+            .retreino
+            .as_ref()
+            .is_some_and(|retreino| retreino.thread.is_finished())
+        {
+            return;
+        }
+        let retreino = self.retreino.take().unwrap();
+        self.estado_retreino = match retreino.thread.join() {
+            Ok(Some((previsor, resultado))) => {
+                self.resultado_retreino = Some(resultado);
+                if resultado.melhorou() {
+                    self.candidato = Some(previsor);
+                    EstadoRetreino::AguardandoConfirmacao
+                } else {
+                    EstadoRetreino::Descartado
+                }
+            }
+            Ok(None) => EstadoRetreino::Cancelado,
+            Err(_) => EstadoRetreino::Falhou,
+        };
+    }
+
+    /*
+    Salva offsets, amostras registradas, amplitudes e o histórico de sessões no perfil em destino.
+    Se o previsor foi adaptado, ele vai para <destino>.modelo.
+    */
+    pub fn salvar_perfil(
+        &mut self,
+        id: &str,
+        destino: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.verificar_retreino();
+        let mut perfil = match self.perfil.take() {
+            Some(perfil) if perfil.id == id => perfil,
+            _ => Perfil::new(id),
+        };
+        if self.modelo_adaptado {
+            let endereco_modelo = format!("{}.modelo", destino);
+            self.previsor.as_ref().unwrap().salvar(&endereco_modelo)?;
+            perfil.modelo = Path::new(&endereco_modelo)
+                .file_name()
+                .map(|nome| nome.to_string_lossy().into_owned());
+        }
+        perfil.offset_esq = self.pes.esq.offset;
+        perfil.offset_dir = self.pes.dir.offset;
+        perfil.amostras = self.registrados.clone();
+        perfil.amplitudes_esq = self.pes.esq.amplitudes;
+        perfil.amplitudes_dir = self.pes.dir.amplitudes;
+        perfil.dificuldade = Some(self.dificuldade.parametros());
+        if let Some(sessao) = self.sessao {
+            perfil.registrar_sessao(
+                RegistroSessao {
+                    sessao,
+                    data: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap()
+                        .as_secs(),
+                    amostras: self.registrados.len(),
+                    arco_esq: self.pes.esq.arco,
+                    arco_dir: self.pes.dir.arco,
+                    fadiga: self.fadiga.resumo_sessao().0,
+                    compensacao: self.fadiga.resumo_sessao().1,
+                    ..Default::default()
+                }
+                .com_resultado(self.resultado_retreino),
+            );
+        }
+        let resultado = perfil.salvar(destino);
+        self.perfil = Some(perfil);
+        resultado
+    }
+
+    /*
+    Restaura um perfil salvo. O previsor adaptado é sempre uma rede neural,
+    já que é o único que suporta transferência.
+    */
+    pub fn carregar_perfil(&mut self, endereco: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let perfil = Perfil::carregar(endereco)?;
+        if let Some(modelo) = perfil.endereco_modelo(endereco) {
+            self.previsor = Some(Box::new(Neural::carregar(&modelo)?));
+            self.modelo_adaptado = true;
+        }
+        self.pes.esq.offset = perfil.offset_esq;
+        self.pes.dir.offset = perfil.offset_dir;
+        self.pes.esq.amplitudes = perfil.amplitudes_esq;
+        self.pes.dir.amplitudes = perfil.amplitudes_dir;
+        self.registrados = perfil.amostras.clone();
+        self.fadiga.definir_linha_base(&self.registrados);
+        if let Some(parametros) = perfil.dificuldade {
+            self.dificuldade = Dificuldade::new(Default::default(), parametros);
+        }
+        self.perfil = Some(perfil);
+        Ok(())
+    }
+
     pub fn ativar_modo_conexao_imediata(&mut self, _max_conexoes: usize) {
         println!("Inultilizado");
     }
@@ -189,6 +738,18 @@ impl Papete {
     }
 }
 
+/*
+Um retreino em andamento é cancelado e esperado, para a thread não sobreviver à Papete
+*/
+impl Drop for Papete {
+    fn drop(&mut self) {
+        self.cancelar_retreino();
+        if let Some(retreino) = self.retreino.take() {
+            let _ = retreino.thread.join();
+        }
+    }
+}
+
 impl Previsor for Papete {
     fn calcular_de_dataset(dataset: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
         match Neural::calcular_de_dataset(dataset) {
@@ -196,6 +757,16 @@ impl Previsor for Papete {
             Err(e) => Err(e),
         }
     }
+    fn calcular_de_dataset_com(
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        match Neural::calcular_de_dataset_com(dataset, pesos_amostras, config) {
+            Ok(n) => Ok(Papete::com_previsor(Box::new(n))),
+            Err(e) => Err(e),
+        }
+    }
     fn carregar(endereco: &str) -> Result<Self, Box<dyn std::error::Error>> {
         match Neural::carregar(endereco) {
             Ok(n) => Ok(Papete::com_previsor(Box::new(n))),
@@ -212,7 +783,409 @@ impl Previsor for Papete {
     fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
         self.previsor.as_mut().unwrap().prever_batch(entrada)
     }
-    fn transferir(&mut self, dataset: &[DadoPapete]) {
+    fn caracteristicas(&self) -> EspecCaracteristicas {
+        self.previsor.as_ref().unwrap().caracteristicas()
+    }
+    fn prever_caracteristicas(&mut self, caracteristicas: &[f32], dado: DadoPapete) -> Movimento {
+        self.previsor
+            .as_mut()
+            .unwrap()
+            .prever_caracteristicas(caracteristicas, dado)
+    }
+    fn transferir(&mut self, dataset: &[DadoPapete]) -> ResultadoTransferencia {
         self.previsor.as_mut().unwrap().transferir(dataset)
     }
+    /*
+    Vale para o previsor atual, usado por transferir, e fica guardada para os próximos
+    retreinos, que rodam sobre uma cópia do previsor
+    */
+    fn configurar_transferencia(&mut self, config: &ConfigTransferencia, populacao: &[DadoPapete]) {
+        if let Some(previsor) = self.previsor.as_mut() {
+            previsor.configurar_transferencia(config, populacao);
+        }
+        self.transferencia = Some((config.clone(), populacao.to_vec()));
+    }
+    fn suporta_transferencia(&self) -> bool {
+        self.previsor
+            .as_ref()
+            .is_some_and(|p| p.suporta_transferencia())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::comm::fonte::FonteMemoria;
+    use crate::papete::*;
+
+    use std::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    //classifica só pelo pitch, para o teste ver qual offset foi descontado
+    //(dorsiflexão é pitch negativo)
+    struct PrevisorFalso;
+
+    impl Previsor for PrevisorFalso {
+        fn calcular_de_dataset(_: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorFalso)
+        }
+        fn calcular_de_dataset_com(
+            _: &[DadoPapete],
+            _: Option<&[f32]>,
+            _: &ConfigTreino,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorFalso)
+        }
+        fn carregar(_: &str) -> Result<Self, Box<dyn std::error::Error>> {
+            Ok(PrevisorFalso)
+        }
+        fn salvar(&self, _: &str) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+        fn prever(&mut self, entrada: DadoPapete) -> Movimento {
+            if entrada.pitch < -0.1 {
+                Movimento::Dorsiflexao
+            } else if entrada.pitch > 0.1 {
+                Movimento::Flexao
+            } else {
+                Movimento::Repouso
+            }
+        }
+        fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
+            entrada.iter().map(|d| self.prever(*d)).collect()
+        }
+        fn transferir(&mut self, _: &[DadoPapete]) -> ResultadoTransferencia {
+            ResultadoTransferencia {
+                acuracia_antes: 0.0,
+                acuracia_depois: 0.0,
+            }
+        }
+    }
+
+    #[test]
+    fn lados_consistentes() {
+        let fonte = FonteMemoria::new();
+        let mut papete = Papete::com_fonte(Some(Box::new(PrevisorFalso)), Box::new(fonte.clone()));
+        papete.configurar_suavizacao(ConfigSuavizacao::desligada());
+        //repousos diferentes em cada pé; a luva não é pé
+        fonte.definir("papE", &[0.5, 0.1]);
+        fonte.definir("papD", &[-0.5, -0.1]);
+        fonte.definir("luvaE", &[0.9, 0.9]);
+
+        let dados = papete.obter_dados();
+        assert!(dados.esq.unwrap().lado_esq && dados.esq.unwrap().pitch == 0.5);
+        assert!(!dados.dir.unwrap().lado_esq && dados.dir.unwrap().pitch == -0.5);
+
+        papete.iniciar_sessao(0);
+        for _ in 0..CapturaOffset::default().tamanho_janela {
+            assert!(!papete.registrar(Movimento::Repouso));
+        }
+        assert_eq!(papete.pes[Lado::Esquerdo].offset.unwrap().pitch, 0.5);
+        assert_eq!(papete.pes[Lado::Direito].offset.unwrap().pitch, -0.5);
+        assert!(papete.registrar(Movimento::Repouso));
+        assert!(papete
+            .registrados
+            .iter()
+            .all(|d| d.pitch.abs() < 1e-6 && d.roll.abs() < 1e-6));
+
+        //só o pé esquerdo levanta a ponta
+        fonte.definir("papE", &[0.2, 0.1]);
+        let bilateral = papete.obter_movimento_bilateral();
+        assert_eq!(bilateral.esq(), Some(Movimento::Dorsiflexao));
+        assert_eq!(bilateral.dir(), Some(Movimento::Repouso));
+        assert_eq!(papete.obter_movimento(), Movimento::Dorsiflexao);
+
+        papete.registrar(Movimento::Dorsiflexao);
+        let esq = papete
+            .registrados
+            .iter()
+            .rev()
+            .find(|d| d.lado_esq)
+            .unwrap();
+        assert!((esq.pitch + 0.3).abs() < 1e-6);
+        assert_eq!(esq.movimento, Some(Movimento::Dorsiflexao));
+
+        //roteiro: o pé direito desconecta por um quadro e volta no seguinte
+        fonte.adicionar_quadro(vec![("papE".to_string(), vec![0.5, 0.1])]);
+        let bilateral = papete.obter_movimento_bilateral();
+        assert_eq!(bilateral.esq(), Some(Movimento::Repouso));
+        assert_eq!(bilateral.dir(), None);
+        assert_eq!(papete.obter_conexoes().len(), 1);
+        let bilateral = papete.obter_movimento_bilateral();
+        assert_eq!(bilateral.esq(), Some(Movimento::Dorsiflexao));
+        assert_eq!(bilateral.dir(), Some(Movimento::Repouso));
+        //a luva volta junto
+        assert_eq!(papete.obter_conexoes().len(), 3);
+
+        //com suavização, uma previsão isolada só aparece no fluxo bruto
+        papete.configurar_suavizacao(ConfigSuavizacao::default());
+        fonte.definir("papE", &[0.2, 0.1]);
+        let previsao = papete.obter_previsao();
+        assert_eq!(previsao.bruto, Movimento::Dorsiflexao);
+        assert_eq!(previsao.suavizado, Movimento::Repouso);
+        assert_eq!(papete.previsao(Lado::Esquerdo), Some(previsao));
+    }
+
+    /*
+    Previsor que só termina a transferência quando o teste libera, respeitando o cancelamento.
+    Depois de adaptado, classifica tudo como eversão. Cada transferência anota a qtd_replay
+    configurada no previsor que a executou.
+    */
+    #[derive(Clone)]
+    struct PrevisorRetreino {
+        liberado: Arc<AtomicBool>,
+        acuracia_depois: f32,
+        adaptado: bool,
+        qtd_replay: usize,
+        replay_usado: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl Previsor for PrevisorRetreino {
+        fn calcular_de_dataset(_: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>> {
+            simple_error::bail!("só para testes")
+        }
+        fn calcular_de_dataset_com(
+            _: &[DadoPapete],
+            _: Option<&[f32]>,
+            _: &ConfigTreino,
+        ) -> Result<Self, Box<dyn std::error::Error>> {
+            simple_error::bail!("só para testes")
+        }
+        fn carregar(_: &str) -> Result<Self, Box<dyn std::error::Error>> {
+            simple_error::bail!("só para testes")
+        }
+        fn salvar(&self, _: &str) -> Result<(), Box<dyn std::error::Error>> {
+            Ok(())
+        }
+        fn prever(&mut self, _: DadoPapete) -> Movimento {
+            if self.adaptado {
+                Movimento::Eversao
+            } else {
+                Movimento::Repouso
+            }
+        }
+        fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento> {
+            entrada.iter().map(|d| self.prever(*d)).collect()
+        }
+        fn transferir(&mut self, entrada: &[DadoPapete]) -> ResultadoTransferencia {
+            self.transferir_controlado(entrada, &ControleTreino::default())
+                .unwrap()
+        }
+        fn transferir_controlado(
+            &mut self,
+            _: &[DadoPapete],
+            controle: &ControleTreino,
+        ) -> Option<ResultadoTransferencia> {
+            controle.informar(0.5);
+            while !self.liberado.load(Ordering::Relaxed) {
+                if controle.cancelado() {
+                    return None;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+            self.adaptado = true;
+            self.replay_usado.lock().unwrap().push(self.qtd_replay);
+            controle.informar(1.0);
+            Some(ResultadoTransferencia {
+                acuracia_antes: 0.5,
+                acuracia_depois: self.acuracia_depois,
+            })
+        }
+        fn configurar_transferencia(&mut self, config: &ConfigTransferencia, _: &[DadoPapete]) {
+            self.qtd_replay = config.qtd_replay;
+        }
+        fn suporta_transferencia(&self) -> bool {
+            true
+        }
+        fn clonar(&self) -> Result<Box<dyn Previsor>, Box<dyn std::error::Error>> {
+            Ok(Box::new(self.clone()))
+        }
+    }
+
+    fn papete_retreino(acuracia_depois: f32) -> (Papete, Arc<AtomicBool>) {
+        let liberado = Arc::new(AtomicBool::new(false));
+        let previsor = PrevisorRetreino {
+            liberado: liberado.clone(),
+            acuracia_depois,
+            adaptado: false,
+            qtd_replay: 0,
+            replay_usado: Arc::default(),
+        };
+        let papete = Papete::com_fonte(Some(Box::new(previsor)), Box::new(FonteMemoria::new()));
+        (papete, liberado)
+    }
+
+    fn esperar_retreino(papete: &mut Papete) -> EstadoRetreino {
+        while papete.estado_retreino() == EstadoRetreino::Treinando {
+            thread::sleep(Duration::from_millis(1));
+        }
+        papete.estado_retreino()
+    }
+
+    fn previsao_atual(papete: &mut Papete) -> Movimento {
+        let dado = DadoPapete::basico(0.0, 0.0, true);
+        papete.previsor.as_mut().unwrap().prever(dado)
+    }
+
+    #[test]
+    fn retreino() {
+        let (mut papete, liberado) = papete_retreino(0.9);
+        assert!(!papete.iniciar_retreino());
+        papete.registrados = vec![DadoPapete::basico(0.0, 0.0, true)];
+
+        //começa, informa o progresso e não deixa começar outro ao mesmo tempo
+        assert!(papete.iniciar_retreino());
+        assert_eq!(papete.estado_retreino(), EstadoRetreino::Treinando);
+        while papete.progresso_retreino() < 0.5 {
+            thread::sleep(Duration::from_millis(1));
+        }
+        assert!(!papete.iniciar_retreino());
+
+        //cancelado, o previsor atual continua
+        papete.cancelar_retreino();
+        assert_eq!(esperar_retreino(&mut papete), EstadoRetreino::Cancelado);
+        assert!(papete.resultado_retreino().is_none());
+        assert_eq!(previsao_atual(&mut papete), Movimento::Repouso);
+
+        //melhorou: a troca espera a confirmação
+        liberado.store(true, Ordering::Relaxed);
+        assert!(papete.iniciar_retreino());
+        assert_eq!(
+            esperar_retreino(&mut papete),
+            EstadoRetreino::AguardandoConfirmacao
+        );
+        assert_eq!(papete.progresso_retreino(), 1.0);
+        assert_eq!(previsao_atual(&mut papete), Movimento::Repouso);
+        assert!(papete.manter_retreino());
+        assert_eq!(papete.estado_retreino(), EstadoRetreino::Concluido);
+        assert_eq!(previsao_atual(&mut papete), Movimento::Eversao);
+        assert!(papete.modelo_adaptado);
+        assert!(!papete.manter_retreino());
+
+        //descartado pelo jogo
+        let (mut papete, liberado) = papete_retreino(0.9);
+        papete.registrados = vec![DadoPapete::basico(0.0, 0.0, true)];
+        liberado.store(true, Ordering::Relaxed);
+        assert!(papete.iniciar_retreino());
+        esperar_retreino(&mut papete);
+        papete.descartar_retreino();
+        assert_eq!(papete.estado_retreino(), EstadoRetreino::Descartado);
+        assert!(!papete.manter_retreino());
+        assert_eq!(previsao_atual(&mut papete), Movimento::Repouso);
+
+        //piorou: nem chega a esperar confirmação
+        let (mut papete, liberado) = papete_retreino(0.1);
+        papete.registrados = vec![DadoPapete::basico(0.0, 0.0, true)];
+        liberado.store(true, Ordering::Relaxed);
+        assert!(papete.iniciar_retreino());
+        assert_eq!(esperar_retreino(&mut papete), EstadoRetreino::Descartado);
+        assert!(!papete.resultado_retreino().unwrap().melhorou());
+        assert!(!papete.manter_retreino());
+        assert_eq!(previsao_atual(&mut papete), Movimento::Repouso);
+
+        //soltar a Papete no meio do retreino cancela e espera a thread
+        let (mut papete, _) = papete_retreino(0.9);
+        papete.registrados = vec![DadoPapete::basico(0.0, 0.0, true)];
+        assert!(papete.iniciar_retreino());
+        drop(papete);
+    }
+
+    #[test]
+    fn transferencia_configurada() {
+        let replay_usado: Arc<Mutex<Vec<usize>>> = Arc::default();
+        let previsor = PrevisorRetreino {
+            liberado: Arc::new(AtomicBool::new(true)),
+            acuracia_depois: 0.9,
+            adaptado: false,
+            qtd_replay: 0,
+            replay_usado: replay_usado.clone(),
+        };
+        let mut papete = Papete::com_fonte(Some(Box::new(previsor)), Box::new(FonteMemoria::new()));
+        let config = ConfigTransferencia {
+            qtd_replay: 7,
+            ..Default::default()
+        };
+        papete.configurar_transferencia(&config, &[DadoPapete::basico(0.0, 0.0, true)]);
+        let dados = vec![DadoPapete::basico(0.0, 0.0, true)];
+
+        //tanto a transferência direta quanto o retreino usam a configuração
+        papete.transferir(&dados);
+        papete.registrados = dados;
+        assert!(papete.iniciar_retreino());
+        esperar_retreino(&mut papete);
+        assert_eq!(*replay_usado.lock().unwrap(), vec![7, 7]);
+    }
+
+    #[test]
+    fn exercicio_pe_direito() {
+        let fonte = FonteMemoria::new();
+        let mut papete = Papete::com_fonte(Some(Box::new(PrevisorFalso)), Box::new(fonte.clone()));
+        papete.configurar_suavizacao(ConfigSuavizacao::desligada());
+        fonte.definir("papE", &[0.0, 0.0]);
+        fonte.definir("papD", &[0.0, 0.0]);
+        papete.iniciar_sessao(0);
+        for _ in 0..CapturaOffset::default().tamanho_janela {
+            papete.obter_movimento();
+        }
+        assert!(papete.pes[Lado::Direito].offset.is_some());
+        papete.iniciar_exercicio(ConfigExercicio {
+            lado: Some(Lado::Direito),
+            duracao_min: std::time::Duration::ZERO,
+            ..ConfigExercicio::new(Movimento::Dorsiflexao, 1, 2)
+        });
+
+        //o jogo só chama obter_movimento, que retorna o pé esquerdo parado;
+        //a contagem começa no repouso
+        papete.obter_movimento();
+        for _ in 0..2 {
+            fonte.definir("papD", &[-0.3, 0.0]);
+            assert_eq!(papete.obter_movimento(), Movimento::Repouso);
+            fonte.definir("papD", &[0.0, 0.0]);
+            assert_eq!(papete.obter_movimento(), Movimento::Repouso);
+        }
+
+        let progresso = papete.progresso_exercicio().unwrap();
+        assert_eq!(progresso.total, 2);
+        assert!(progresso.concluido);
+        assert!(
+            (papete.arco_movimento(Lado::Direito).dorsiflexao - 0.3f32.to_degrees()).abs() < 1e-3
+        );
+        assert_eq!(papete.arco_movimento(Lado::Esquerdo).dorsiflexao, 0.0);
+        assert_eq!(
+            papete.previsao(Lado::Direito).unwrap().suavizado,
+            Movimento::Repouso
+        );
+    }
+
+    #[test]
+    fn eventos_por_pe() {
+        let fonte = FonteMemoria::new();
+        let mut papete = Papete::com_fonte(Some(Box::new(PrevisorFalso)), Box::new(fonte.clone()));
+        papete.configurar_suavizacao(ConfigSuavizacao::desligada());
+        let canal = papete.assinar_eventos();
+        fonte.definir("papE", &[0.0, 0.0]);
+        fonte.definir("papD", &[0.0, 0.0]);
+        papete.iniciar_sessao(0);
+        for _ in 0..CapturaOffset::default().tamanho_janela {
+            papete.obter_movimento_bilateral();
+        }
+
+        fonte.definir("papD", &[-0.3, 0.0]);
+        papete.obter_movimento_bilateral();
+        fonte.definir("papD", &[0.0, 0.0]);
+        papete.obter_movimento_bilateral();
+
+        let eventos: Vec<EventoMovimento> = papete.drenar_eventos(10).collect();
+        assert_eq!(eventos.len(), 2);
+        assert!(eventos
+            .iter()
+            .all(|e| !e.lado_esq && e.movimento == Movimento::Dorsiflexao));
+        assert_eq!(eventos[1].pico_pitch, -0.3);
+        assert_eq!(papete.drenar_eventos(10).count(), 0);
+        assert_eq!(canal.try_iter().collect::<Vec<_>>(), eventos);
+    }
 }
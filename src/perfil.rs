@@ -0,0 +1,192 @@
+/*
+Perfil do paciente: o que a calibração produz e que vale a pena guardar entre sessões,
+para não recalibrar tudo a cada vez que a Papete é liberada.
+Salvo em JSON; o previsor adaptado, se houver, fica num arquivo ao lado.
+*/
+
+use crate::{
+    arco::ArcoMovimento, dado_papete::DadoPapete, dificuldade::ParametrosDificuldade, lado::Lado,
+    previsor::ResultadoTransferencia,
+};
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/*
+Versão atual do formato. Ao mudar o formato, incremente e converta as versões antigas em migrar.
+*/
+pub static VERSAO_PERFIL: u32 = 1;
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegistroSessao {
+    pub sessao: u32,
+    //segundos desde UNIX_EPOCH
+    pub data: u64,
+    pub amostras: usize,
+    //acurácia na calibração antes e depois do retreino, se houve
+    pub acuracia_antes: Option<f32>,
+    pub acuracia_depois: Option<f32>,
+    //arco de movimento (graus) alcançado em cada pé na sessão
+    pub arco_esq: ArcoMovimento,
+    pub arco_dir: ArcoMovimento,
+    //se houve alertas de fadiga e compensação durante a sessão
+    pub fadiga: bool,
+    pub compensacao: bool,
+}
+
+impl RegistroSessao {
+    pub fn com_resultado(mut self, resultado: Option<ResultadoTransferencia>) -> Self {
+        if let Some(resultado) = resultado {
+            self.acuracia_antes = Some(resultado.acuracia_antes);
+            self.acuracia_depois = Some(resultado.acuracia_depois);
+        }
+        self
+    }
+}
+
+/*
+Campos ausentes num perfil antigo ficam com o valor padrão.
+- amplitudes: amplitude máxima (rad) de cada pé em cada movimento, indexada por Movimento::as_usize;
+- modelo: arquivo do previsor adaptado, relativo ao perfil;
+- dificuldade: onde a progressão parou na última sessão.
+*/
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Perfil {
+    pub versao: u32,
+    pub id: String,
+    pub offset_esq: Option<DadoPapete>,
+    pub offset_dir: Option<DadoPapete>,
+    pub amostras: Vec<DadoPapete>,
+    pub modelo: Option<String>,
+    pub amplitudes_esq: [f32; 5],
+    pub amplitudes_dir: [f32; 5],
+    pub historico: Vec<RegistroSessao>,
+    pub dificuldade: Option<ParametrosDificuldade>,
+}
+
+impl Perfil {
+    pub fn new(id: &str) -> Perfil {
+        Perfil {
+            versao: VERSAO_PERFIL,
+            id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn carregar(endereco: &str) -> Result<Perfil, Box<dyn std::error::Error>> {
+        let conteudo = std::fs::read_to_string(endereco)?;
+        let valor: serde_json::Value = serde_json::from_str(&conteudo)?;
+        let versao = valor
+            .get("versao")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(VERSAO_PERFIL as u64) as u32;
+        if versao > VERSAO_PERFIL {
+            simple_error::bail!(
+                "Perfil na versão {}, mais nova que a suportada ({})",
+                versao,
+                VERSAO_PERFIL
+            );
+        }
+        let mut perfil: Perfil = serde_json::from_value(migrar(valor, versao))?;
+        perfil.versao = VERSAO_PERFIL;
+        Ok(perfil)
+    }
+
+    pub fn salvar(&self, destino: &str) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::write(destino, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /*
+    Endereço do previsor adaptado, resolvido a partir da pasta do perfil
+    */
+    pub fn endereco_modelo(&self, endereco_perfil: &str) -> Option<String> {
+        self.modelo.as_ref().map(|modelo| {
+            Path::new(endereco_perfil)
+                .with_file_name(modelo)
+                .to_string_lossy()
+                .into_owned()
+        })
+    }
+
+    /*
+    Adiciona a sessão ao histórico, ou atualiza se ela já estava lá
+    */
+    pub fn registrar_sessao(&mut self, registro: RegistroSessao) {
+        match self
+            .historico
+            .iter_mut()
+            .find(|r| r.sessao == registro.sessao)
+        {
+            Some(r) => *r = registro,
+            None => self.historico.push(registro),
+        }
+    }
+
+    /*
+    Maior arco de movimento do pé em todas as sessões
+    */
+    pub fn arco_maximo(&self, lado: Lado) -> ArcoMovimento {
+        self.historico
+            .iter()
+            .map(|r| match lado {
+                Lado::Esquerdo => r.arco_esq,
+                Lado::Direito => r.arco_dir,
+            })
+            .fold(ArcoMovimento::default(), |a, b| a.maximo(&b))
+    }
+}
+
+/*
+Converte o JSON de uma versão antiga para a atual. Na versão 1 ainda não há o que converter.
+*/
+fn migrar(valor: serde_json::Value, _versao: u32) -> serde_json::Value {
+    valor
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::movimento::Movimento;
+    use crate::perfil::*;
+
+    #[test]
+    fn ida_e_volta() {
+        let mut perfil = Perfil::new("paciente");
+        perfil.offset_esq = Some(DadoPapete::basico(0.1, -0.2, true));
+        perfil.amostras.push(DadoPapete::completo(
+            0.3,
+            0.0,
+            true,
+            Movimento::Dorsiflexao,
+            7,
+        ));
+        perfil.registrar_sessao(RegistroSessao {
+            sessao: 7,
+            ..Default::default()
+        });
+        perfil.registrar_sessao(RegistroSessao {
+            sessao: 7,
+            amostras: 1,
+            ..Default::default()
+        });
+
+        let destino =
+            std::env::temp_dir().join(format!("papete_perfil_{}.json", std::process::id()));
+        let destino = destino.to_str().unwrap();
+        perfil.salvar(destino).unwrap();
+        let lido = Perfil::carregar(destino).unwrap();
+        std::fs::remove_file(destino).unwrap();
+        assert_eq!(lido.id, "paciente");
+        assert_eq!(lido.historico.len(), 1);
+        assert_eq!(lido.historico[0].amostras, 1);
+        assert_eq!(lido.amostras[0].movimento, Some(Movimento::Dorsiflexao));
+        assert!(lido.offset_dir.is_none());
+
+        //perfil antigo, sem versão nem os campos novos
+        let antigo: Perfil = serde_json::from_str(r#"{"id": "x"}"#).unwrap();
+        assert_eq!(antigo.id, "x");
+        assert!(antigo.historico.is_empty());
+    }
+}
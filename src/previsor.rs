@@ -1,7 +1,58 @@
+use crate::balanceamento::ConfigTreino;
+use crate::caracteristicas::EspecCaracteristicas;
 use crate::csv_helper;
 use crate::dado_papete::DadoPapete;
 use crate::movimento::Movimento;
-pub trait Previsor {
+use crate::neural::ConfigTransferencia;
+
+use std::sync::{
+    atomic::{AtomicBool, AtomicU32, Ordering},
+    Arc,
+};
+
+/*
+Acurácia sobre os dados de calibração antes e depois de uma transferência.
+Permite ao jogo decidir se mantém ou descarta o modelo adaptado.
+*/
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct ResultadoTransferencia {
+    pub acuracia_antes: f32,
+    pub acuracia_depois: f32,
+}
+
+impl ResultadoTransferencia {
+    pub fn melhorou(&self) -> bool {
+        self.acuracia_depois >= self.acuracia_antes
+    }
+}
+
+/*
+Compartilhado entre quem pede um treino e a thread que o executa:
+um lado informa o progresso (0 a 1), o outro pode pedir o cancelamento.
+*/
+#[derive(Clone, Default)]
+pub struct ControleTreino {
+    progresso: Arc<AtomicU32>,
+    cancelado: Arc<AtomicBool>,
+}
+
+impl ControleTreino {
+    pub fn informar(&self, progresso: f32) {
+        self.progresso.store(progresso.to_bits(), Ordering::Relaxed);
+    }
+    pub fn progresso(&self) -> f32 {
+        f32::from_bits(self.progresso.load(Ordering::Relaxed))
+    }
+    pub fn cancelar(&self) {
+        self.cancelado.store(true, Ordering::Relaxed);
+    }
+    pub fn cancelado(&self) -> bool {
+        self.cancelado.load(Ordering::Relaxed)
+    }
+}
+
+pub trait Previsor: Send {
     fn calcular_de_dataset_addr(endereco_dataset: &str) -> Result<Self, Box<dyn std::error::Error>>
     where
         Self: Sized,
@@ -10,6 +61,16 @@ pub trait Previsor {
         Self::calcular_de_dataset(&dados)
     }
     fn calcular_de_dataset(dataset: &[DadoPapete]) -> Result<Self, Box<dyn std::error::Error>>
+    where
+        Self: Sized;
+    /*
+    Treino com pesos por amostra (opcional) e compensação do desbalanceamento entre movimentos
+    */
+    fn calcular_de_dataset_com(
+        dataset: &[DadoPapete],
+        pesos_amostras: Option<&[f32]>,
+        config: &ConfigTreino,
+    ) -> Result<Self, Box<dyn std::error::Error>>
     where
         Self: Sized;
 
@@ -21,5 +82,78 @@ pub trait Previsor {
     fn prever(&mut self, entrada: DadoPapete) -> Movimento;
 
     fn prever_batch(&mut self, entrada: &[DadoPapete]) -> Vec<Movimento>;
-    fn transferir(&mut self, entrada: &[DadoPapete]);
+
+    /*
+    Características que o previsor espera em prever_caracteristicas. O padrão é a instantânea
+    (pitch, roll e lado), que é o que prever já recebe.
+    */
+    fn caracteristicas(&self) -> EspecCaracteristicas {
+        EspecCaracteristicas::instantanea()
+    }
+
+    /*
+    Previsão a partir do vetor descrito por caracteristicas(), calculado pela Papete sobre o
+    fluxo do pé; dado é a amostra mais recente. O padrão ignora o vetor e chama prever.
+    */
+    fn prever_caracteristicas(&mut self, _caracteristicas: &[f32], dado: DadoPapete) -> Movimento {
+        self.prever(dado)
+    }
+
+    fn transferir(&mut self, entrada: &[DadoPapete]) -> ResultadoTransferencia;
+
+    /*
+    Igual a transferir, mas informa o progresso e para se o controle for cancelado.
+    Retorna None quando cancelado.
+    */
+    fn transferir_controlado(
+        &mut self,
+        entrada: &[DadoPapete],
+        controle: &ControleTreino,
+    ) -> Option<ResultadoTransferencia> {
+        let resultado = self.transferir(entrada);
+        controle.informar(1.0);
+        Some(resultado)
+    }
+
+    /*
+    Como as próximas transferências são feitas e de onde vêm as amostras de replay.
+    Previsores sem essas opções ignoram.
+    */
+    fn configurar_transferencia(
+        &mut self,
+        _config: &ConfigTransferencia,
+        _populacao: &[DadoPapete],
+    ) {
+    }
+
+    /*
+    Se transferir está implementado. Previsores que retornam false não podem ser calibrados;
+    quem implementa transferir deve retornar true.
+    */
+    fn suporta_transferencia(&self) -> bool {
+        false
+    }
+
+    /*
+    Cópia independente do previsor, para ser treinada em outra thread
+    */
+    fn clonar(&self) -> Result<Box<dyn Previsor>, Box<dyn std::error::Error>> {
+        simple_error::bail!("Previsor não pode ser clonado")
+    }
+
+    /*
+    Fração dos dados rotulados que o previsor acerta
+    */
+    fn acuracia(&mut self, dataset: &[DadoPapete]) -> f32 {
+        if dataset.is_empty() {
+            return 0.0;
+        }
+        let acertos = self
+            .prever_batch(dataset)
+            .iter()
+            .zip(dataset.iter())
+            .filter(|(obtido, esperado)| Some(**obtido) == esperado.movimento)
+            .count();
+        acertos as f32 / dataset.len() as f32
+    }
 }
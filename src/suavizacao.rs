@@ -0,0 +1,166 @@
+/*
+Pós-processamento das previsões de um pé, para os jogos não verem o movimento piscar
+entre classes perto das fronteiras:
+- voto da maioria numa janela deslizante das últimas previsões;
+- histerese: um movimento novo precisa de limiar_entrada dos votos para assumir, e o atual
+  só é abandonado sem isso quando cai abaixo de limiar_saida;
+- permanência mínima: a troca só acontece se o novo movimento se sustentar por esse tempo.
+*/
+
+use crate::movimento::Movimento;
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+#[derive(Clone, Debug)]
+pub struct ConfigSuavizacao {
+    pub janela: usize,
+    pub limiar_entrada: f32,
+    pub limiar_saida: f32,
+    pub permanencia_minima: Duration,
+}
+
+impl Default for ConfigSuavizacao {
+    fn default() -> Self {
+        ConfigSuavizacao {
+            janela: 5,
+            limiar_entrada: 0.6,
+            limiar_saida: 0.4,
+            permanencia_minima: Duration::from_millis(100),
+        }
+    }
+}
+
+impl ConfigSuavizacao {
+    /*
+    Sem suavização: o suavizado é igual ao bruto
+    */
+    #[allow(dead_code)]
+    pub fn desligada() -> ConfigSuavizacao {
+        ConfigSuavizacao {
+            janela: 1,
+            limiar_entrada: 0.0,
+            limiar_saida: 0.0,
+            permanencia_minima: Duration::ZERO,
+        }
+    }
+}
+
+/*
+As duas saídas para a mesma amostra: os jogos usam a suavizada e os registros de pesquisa a bruta
+*/
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Previsao {
+    pub bruto: Movimento,
+    pub suavizado: Movimento,
+}
+
+#[derive(Clone, Debug)]
+pub struct Suavizador {
+    config: ConfigSuavizacao,
+    janela: VecDeque<Movimento>,
+    estado: Movimento,
+    //movimento que quer assumir e desde quando
+    candidato: Option<(Movimento, Instant)>,
+}
+
+impl Default for Suavizador {
+    fn default() -> Self {
+        Suavizador::new(ConfigSuavizacao::default())
+    }
+}
+
+impl Suavizador {
+    pub fn new(config: ConfigSuavizacao) -> Suavizador {
+        Suavizador {
+            janela: VecDeque::with_capacity(config.janela),
+            config,
+            estado: Movimento::Repouso,
+            candidato: None,
+        }
+    }
+
+    pub fn atualizar(&mut self, bruto: Movimento, agora: Instant) -> Previsao {
+        if self.janela.len() >= self.config.janela.max(1) {
+            self.janela.pop_front();
+        }
+        self.janela.push_back(bruto);
+
+        let mut votos = [0usize; 5];
+        for m in &self.janela {
+            votos[m.as_usize()] += 1;
+        }
+        let fracao = |m: Movimento| votos[m.as_usize()] as f32 / self.janela.len() as f32;
+        //em empate fica o mais recente
+        let mais_votado = self
+            .janela
+            .iter()
+            .rev()
+            .copied()
+            .max_by_key(|m| votos[m.as_usize()])
+            .unwrap();
+
+        let trocar = mais_votado != self.estado
+            && (fracao(mais_votado) >= self.config.limiar_entrada
+                || fracao(self.estado) < self.config.limiar_saida);
+        if trocar {
+            let desde = match self.candidato {
+                Some((m, desde)) if m == mais_votado => desde,
+                _ => agora,
+            };
+            self.candidato = Some((mais_votado, desde));
+            if agora.duration_since(desde) >= self.config.permanencia_minima {
+                self.estado = mais_votado;
+                self.candidato = None;
+            }
+        } else {
+            self.candidato = None;
+        }
+
+        Previsao {
+            bruto,
+            suavizado: self.estado,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn estado(&self) -> Movimento {
+        self.estado
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::suavizacao::*;
+
+    #[test]
+    fn filtra_oscilacao() {
+        use Movimento::*;
+        let mut s = Suavizador::default();
+        let mut agora = Instant::now();
+        let mut passo = |s: &mut Suavizador, m: Movimento| {
+            agora += Duration::from_millis(50);
+            s.atualizar(m, agora).suavizado
+        };
+
+        //uma previsão isolada não muda o estado
+        for m in [Repouso, Repouso, Eversao, Repouso, Repouso] {
+            assert_eq!(passo(&mut s, m), Repouso);
+        }
+        //mudança sustentada: assume depois da maioria e da permanência mínima
+        let saidas: Vec<Movimento> = (0..6).map(|_| passo(&mut s, Dorsiflexao)).collect();
+        assert_eq!(saidas[..3], [Repouso; 3]);
+        assert_eq!(s.estado(), Dorsiflexao);
+
+        //histerese: com 40% ou mais dos votos, o estado atual se mantém
+        for m in [Flexao, Flexao] {
+            assert_eq!(passo(&mut s, m), Dorsiflexao);
+        }
+
+        let mut direto = Suavizador::new(ConfigSuavizacao::desligada());
+        assert_eq!(direto.atualizar(Eversao, agora).suavizado, Eversao);
+    }
+}